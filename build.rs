@@ -1,3 +1,7 @@
 fn main() {
-    embuild::espidf::sysenv::output();
+    // Only the ESP-IDF build links against the SDK, the host and `no_std` builds don't
+    // have one to configure.
+    if std::env::var_os("CARGO_FEATURE_ESP_IDF_SVC").is_some() {
+        embuild::espidf::sysenv::output();
+    }
 }
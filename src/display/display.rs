@@ -1,7 +1,64 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::graphics::FrameBufferView;
+
 pub trait Display {
-    fn clear_display(&mut self) -> anyhow::Result<()>;
+    /// Width of the panel in pixels.
+    fn width(&self) -> u16;
+
+    /// Height of the panel in pixels.
+    fn height(&self) -> u16;
+
+    fn clear_display(&mut self) -> crate::Result<()>;
+
+    fn refresh(&mut self, buffer: FrameBufferView<'_>) -> crate::Result<()>;
+
+    fn refresh_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()>;
+
+    /// Writes an arbitrary, not necessarily contiguous, set of `(line number, data)` rows.
+    ///
+    /// The default implementation sends every row on its own; drivers whose panel accepts
+    /// several rows per write command should override it to send them in one transfer.
+    fn refresh_lines(&mut self, lines: &[(u16, &[u8])]) -> crate::Result<()> {
+        for (line_num, buffer) in lines {
+            self.refresh_line(*line_num, buffer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the rows of `buffer` whose flag in `dirty` is set.
+    ///
+    /// The default implementation collects the rows for `refresh_lines`, or sends them one
+    /// by one without `alloc`; drivers should override it to encode them straight from
+    /// `buffer` without allocating.
+    fn refresh_dirty(&mut self, buffer: FrameBufferView<'_>, dirty: &[bool]) -> crate::Result<()> {
+        let lines = buffer
+            .rows()
+            .zip(dirty)
+            .enumerate()
+            .filter(|(_, (_, dirty))| **dirty)
+            .map(|(line_num, (line, _))| (line_num as u16, line));
+
+        #[cfg(feature = "alloc")]
+        {
+            self.refresh_lines(&lines.collect::<Vec<(u16, &[u8])>>())
+        }
+
+        #[cfg(not(feature = "alloc"))]
+        {
+            for (line_num, line) in lines {
+                self.refresh_line(line_num, line)?;
+            }
 
-    fn refresh(&mut self, buffer: &Vec<Vec<u8>>) -> anyhow::Result<()>;
+            Ok(())
+        }
+    }
 
-    fn refresh_line(&mut self, line_num: u8, buffer: &[u8]) -> anyhow::Result<()>;
+    /// Blocks until everything sent to the display so far has reached the panel, for
+    /// drivers that return before their transfers are finished.
+    fn flush(&mut self) -> crate::Result<()> {
+        Ok(())
+    }
 }
@@ -0,0 +1,203 @@
+use std::mem;
+use std::ptr::NonNull;
+
+use esp_idf_svc::hal::delay::BLOCK;
+use esp_idf_svc::sys::{
+    esp, heap_caps_free, heap_caps_malloc, spi_device_acquire_bus, spi_device_get_trans_result,
+    spi_device_handle_t, spi_device_queue_trans, spi_device_release_bus, spi_transaction_t,
+    spi_transaction_t__bindgen_ty_1, MALLOC_CAP_DMA, SPI_TRANS_CS_KEEP_ACTIVE,
+};
+
+use crate::Error;
+
+/// Largest chunk a single DMA descriptor can carry on the ESP32, a multiple of 4.
+const DMA_MAX_CHUNK: usize = 4092;
+
+/// Transmit buffer allocated in memory the SPI DMA engine can read from.
+pub struct DmaBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl DmaBuffer {
+    pub fn new(len: usize) -> crate::Result<Self> {
+        let ptr = unsafe { heap_caps_malloc(len, MALLOC_CAP_DMA) } as *mut u8;
+
+        match NonNull::new(ptr) {
+            Some(ptr) => Ok(Self { ptr, len }),
+            None => Err(Error::DmaAlloc { len }),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for DmaBuffer {
+    fn drop(&mut self) {
+        unsafe { heap_caps_free(self.ptr.as_ptr() as *mut _) };
+    }
+}
+
+// The buffer is exclusively owned, the pointer is never shared outside of `DmaBuffer`.
+unsafe impl Send for DmaBuffer {}
+
+/// Exclusive use of an SPI bus, released again when dropped unless it is kept for a
+/// transfer left running.
+struct BusLock(spi_device_handle_t);
+
+impl BusLock {
+    fn acquire(device: spi_device_handle_t) -> crate::Result<Self> {
+        esp!(unsafe { spi_device_acquire_bus(device, BLOCK) })?;
+
+        Ok(Self(device))
+    }
+
+    /// Takes over the bus `queue` kept acquired for a transfer.
+    fn held(device: spi_device_handle_t) -> Self {
+        Self(device)
+    }
+
+    /// Leaves the bus acquired after returning, for the transfer still queued on it.
+    fn keep(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for BusLock {
+    fn drop(&mut self) {
+        unsafe { spi_device_release_bus(self.0) };
+    }
+}
+
+/// A transfer out of a [`DmaBuffer`] queued on an SPI device, which keeps running while the
+/// CPU does other work.
+///
+/// Transfers larger than a DMA descriptor are split into several transactions, with chip
+/// select held active between them so the panel sees a single command.
+pub struct QueuedTransfer {
+    device: spi_device_handle_t,
+    chunk_size: usize,
+    transactions: Vec<spi_transaction_t>,
+    pending: usize,
+}
+
+impl QueuedTransfer {
+    /// `max_transfer_size` is the limit the SPI bus was configured with for DMA transfers.
+    pub fn new(device: spi_device_handle_t, max_transfer_size: usize) -> Self {
+        Self {
+            device,
+            chunk_size: Self::chunk_size(max_transfer_size),
+            transactions: Vec::new(),
+            pending: 0,
+        }
+    }
+
+    /// Size of the transactions a transfer is split into.
+    pub fn chunk_size(max_transfer_size: usize) -> usize {
+        (max_transfer_size.min(DMA_MAX_CHUNK) & !0b11).max(4)
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending > 0
+    }
+
+    /// Queues the first `len` bytes of `buffer` and returns without waiting for them to be
+    /// sent. `buffer` must not be modified or dropped before [`QueuedTransfer::wait`].
+    pub fn queue(&mut self, buffer: &mut DmaBuffer, len: usize) -> crate::Result<()> {
+        self.wait()?;
+
+        if len > buffer.capacity() {
+            return Err(Error::DmaTransfer {
+                len,
+                capacity: buffer.capacity(),
+            });
+        }
+
+        let data = &buffer.as_mut_slice()[..len];
+        let chunks = data.chunks(self.chunk_size);
+        let count = chunks.len();
+
+        self.transactions.clear();
+        self.transactions
+            .extend(chunks.enumerate().map(|(i, chunk)| spi_transaction_t {
+                flags: if i + 1 < count {
+                    SPI_TRANS_CS_KEEP_ACTIVE
+                } else {
+                    0
+                },
+                length: chunk.len() * 8,
+                __bindgen_anon_1: spi_transaction_t__bindgen_ty_1 {
+                    tx_buffer: chunk.as_ptr() as *const _,
+                },
+                ..Default::default()
+            }));
+
+        // keeping chip select active across transactions requires exclusive use of the bus
+        let bus = BusLock::acquire(self.device)?;
+
+        let mut queued = Ok(());
+
+        for transaction in self.transactions.iter_mut() {
+            queued = esp!(unsafe { spi_device_queue_trans(self.device, transaction, BLOCK) });
+
+            if queued.is_err() {
+                break;
+            }
+
+            self.pending += 1;
+        }
+
+        if let Err(err) = queued {
+            // the bus is released once the transactions queued so far are collected,
+            // whether or not that succeeds
+            let _ = self.collect();
+
+            return Err(err.into());
+        }
+
+        bus.keep();
+
+        Ok(())
+    }
+
+    /// Blocks until every queued transaction has been clocked out.
+    pub fn wait(&mut self) -> crate::Result<()> {
+        if !self.is_pending() {
+            return Ok(());
+        }
+
+        let _bus = BusLock::held(self.device);
+
+        self.collect()
+    }
+
+    /// Takes the results of the pending transactions. If one can't be collected, the rest
+    /// are given up on so that the bus can still be released.
+    fn collect(&mut self) -> crate::Result<()> {
+        while self.pending > 0 {
+            let mut done: *mut spi_transaction_t = std::ptr::null_mut();
+
+            if let Err(err) =
+                esp!(unsafe { spi_device_get_trans_result(self.device, &mut done, BLOCK) })
+            {
+                self.pending = 0;
+
+                return Err(err.into());
+            }
+
+            self.pending -= 1;
+        }
+
+        Ok(())
+    }
+}
+
+// The transactions only point into a `DmaBuffer` owned by the same driver, and the device
+// handle is safe to use from any task as long as it isn't used concurrently.
+unsafe impl Send for QueuedTransfer {}
@@ -0,0 +1,115 @@
+use crate::display::Display;
+use crate::graphics::{FrameBufferView, HeapFrameBuffer};
+use crate::Error;
+
+/// A single call made against a [`MockDisplay`], together with the bytes it was given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DisplayCall {
+    Clear,
+    Refresh(HeapFrameBuffer),
+    RefreshLine(u16, Vec<u8>),
+    RefreshLines(Vec<(u16, Vec<u8>)>),
+}
+
+/// In-memory [`Display`] used to test the graphics code on the host.
+///
+/// Every call is recorded in `calls`, and `frame` mirrors what the pixel memory of a real
+/// panel would hold after those calls (set bits are white, like on the Sharp panels).
+pub struct MockDisplay {
+    pub width: u16,
+    pub height: u16,
+    pub calls: Vec<DisplayCall>,
+    pub frame: HeapFrameBuffer,
+}
+
+impl MockDisplay {
+    pub fn new(width: u16, height: u16) -> Self {
+        MockDisplay {
+            width,
+            height,
+            calls: Vec::new(),
+            frame: HeapFrameBuffer::new(width, height),
+        }
+    }
+
+    /// Returns the recorded calls and starts recording from scratch.
+    pub fn take_calls(&mut self) -> Vec<DisplayCall> {
+        std::mem::take(&mut self.calls)
+    }
+
+    /// Returns the frame sent by the most recent `refresh`, if there was one.
+    pub fn last_refresh(&self) -> Option<&HeapFrameBuffer> {
+        self.calls.iter().rev().find_map(|call| match call {
+            DisplayCall::Refresh(buffer) => Some(buffer),
+            _ => None,
+        })
+    }
+
+    fn set_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        if line_num >= self.frame.height() {
+            return Err(Error::LineOutOfBounds {
+                line: line_num,
+                height: self.frame.height(),
+            });
+        }
+
+        if buffer.len() != self.frame.stride() {
+            return Err(Error::LineLength {
+                line: line_num,
+                len: buffer.len(),
+                expected: self.frame.stride(),
+            });
+        }
+
+        self.frame.row_mut(line_num).copy_from_slice(buffer);
+
+        Ok(())
+    }
+}
+
+impl Display for MockDisplay {
+    fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn height(&self) -> u16 {
+        self.height
+    }
+
+    fn clear_display(&mut self) -> crate::Result<()> {
+        self.calls.push(DisplayCall::Clear);
+        self.frame.fill(0xFF);
+
+        Ok(())
+    }
+
+    fn refresh(&mut self, buffer: FrameBufferView<'_>) -> crate::Result<()> {
+        self.calls
+            .push(DisplayCall::Refresh(buffer.to_owned_buffer()));
+        self.frame = buffer.to_owned_buffer();
+
+        Ok(())
+    }
+
+    fn refresh_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        self.calls
+            .push(DisplayCall::RefreshLine(line_num, buffer.to_vec()));
+
+        self.set_line(line_num, buffer)
+    }
+
+    fn refresh_lines(&mut self, lines: &[(u16, &[u8])]) -> crate::Result<()> {
+        self.calls.push(DisplayCall::RefreshLines(
+            lines
+                .iter()
+                .map(|(line_num, buffer)| (*line_num, buffer.to_vec()))
+                .collect(),
+        ));
+
+        for (line_num, buffer) in lines {
+            self.set_line(*line_num, buffer)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,26 @@
+#[allow(clippy::module_inception)]
 pub mod display;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
+pub mod dma_transfer;
+#[cfg(feature = "host")]
+pub mod mock_display;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
 pub mod sharp_memory;
+pub mod sharp_panel;
+pub mod sharp_protocol;
+pub mod sharp_spi;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
+pub mod vcom_task;
 
 pub use display::*;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
+pub use dma_transfer::*;
+#[cfg(feature = "host")]
+pub use mock_display::*;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
 pub use sharp_memory::*;
+pub use sharp_panel::*;
+pub use sharp_protocol::*;
+pub use sharp_spi::*;
+#[cfg(all(feature = "esp-idf-svc", feature = "std"))]
+pub use vcom_task::*;
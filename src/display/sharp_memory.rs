@@ -1,97 +1,333 @@
-use std::vec;
+use std::time::{Duration, Instant};
 
-use esp_idf_svc::hal::gpio::{AnyIOPin, OutputPin};
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use esp_idf_svc::hal::gpio::{AnyIOPin, AnyOutputPin, Output, OutputPin, PinDriver};
 use esp_idf_svc::hal::interrupt::IntrFlags;
 use esp_idf_svc::hal::peripheral::Peripheral;
 use esp_idf_svc::hal::spi::config::{DriverConfig, MODE_0};
 use esp_idf_svc::hal::spi::SpiAnyPins;
 use esp_idf_svc::hal::spi::{
     config::{BitOrder, Config},
-    Dma, SpiDeviceDriver, SpiDriver,
+    Dma, SpiDeviceDriver, SpiDriver, SpiError,
 };
 use esp_idf_svc::hal::units::Hertz;
+use esp_idf_svc::sys::{EspError, ESP_FAIL};
 
-use crate::display::Display;
+use crate::display::{Display, DmaBuffer, QueuedTransfer, SharpPanel, SharpSpiDisplay};
+use crate::graphics::FrameBufferView;
+use crate::Error;
 
-const SHARPMEM_CMD_WRITE_LINE: u8 = 0b00000001;
-const SHARPMEM_CMD_VCOM: u8 = 0b00000010;
-const SHARPMEM_CMD_CLEAR_SCREEN: u8 = 0b00000100;
+/// The ESP-IDF SPI device a [`SharpMemoryDisplay`] sends its commands to.
+///
+/// With DMA, a lone write that fits the DMA capable buffer is copied there and queued, so
+/// it returns while the transfer is still running. Every transaction waits for the one
+/// before to finish first.
+pub struct EspSpiDevice<'a> {
+    device: SpiDeviceDriver<'a, SpiDriver<'a>>,
+    queue: Option<(DmaBuffer, QueuedTransfer)>,
+}
+
+impl EspSpiDevice<'_> {
+    /// Blocks until a queued write has been clocked out.
+    pub fn wait(&mut self) -> crate::Result<()> {
+        match &mut self.queue {
+            Some((_, transfer)) => transfer.wait(),
+            None => Ok(()),
+        }
+    }
+}
 
+impl ErrorType for EspSpiDevice<'_> {
+    type Error = SpiError;
+}
+
+impl SpiDevice for EspSpiDevice<'_> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), SpiError> {
+        self.wait().map_err(spi_error)?;
+
+        match (&mut self.queue, operations) {
+            (Some((buffer, transfer)), [Operation::Write(words)])
+                if words.len() <= buffer.capacity() =>
+            {
+                buffer.as_mut_slice()[..words.len()].copy_from_slice(*words);
+                transfer.queue(buffer, words.len()).map_err(spi_error)
+            }
+            (_, operations) => SpiDevice::transaction(&mut self.device, operations),
+        }
+    }
+}
+
+/// The error of the ESP-IDF call behind a failed queued transfer.
+fn spi_error(err: Error) -> SpiError {
+    match err {
+        Error::Esp(err) => SpiError::other(err),
+        _ => SpiError::other(EspError::from_infallible::<ESP_FAIL>()),
+    }
+}
+
+impl Drop for EspSpiDevice<'_> {
+    fn drop(&mut self) {
+        // the DMA buffer must outlive a transfer that is still running
+        let _ = self.wait();
+    }
+}
+
+/// Sharp memory LCD driven through the ESP-IDF SPI driver, optionally with queued DMA
+/// transfers.
+///
+/// The commands are sent by a [`SharpSpiDisplay`] on an [`EspSpiDevice`], this adds the
+/// panel's pins and keeps track of when VCOM was last inverted, for `VcomTask`.
 pub struct SharpMemoryDisplay<'a> {
-    vcom: u8,
-    device: SpiDeviceDriver<'a, SpiDriver<'a>>,
+    display: SharpSpiDisplay<EspSpiDevice<'a>, Box<[u8]>>,
+    last_vcom_toggle: Instant,
+    disp: Option<PinDriver<'a, AnyOutputPin, Output>>,
+    extmode: Option<PinDriver<'a, AnyOutputPin, Output>>,
+    power: Option<PinDriver<'a, AnyOutputPin, Output>>,
+    /// Cleared by `power_down`, while the panel's supply is cut.
+    powered: bool,
 }
 
 impl<'b> SharpMemoryDisplay<'b> {
+    /// With `dma` enabled commands are copied into a DMA capable buffer and queued, so
+    /// drawing returns while the transfer is still running, see [`Display::flush`]. That
+    /// takes a second buffer of a full frame next to the one commands are encoded into.
+    /// The transfer size of the DMA channel is only a hint, frames larger than it are split.
     pub fn new(
+        panel: SharpPanel,
         freq: Hertz,
+        dma: Dma,
         sclk: impl Peripheral<P = impl OutputPin> + 'b,
         sdo: impl Peripheral<P = impl OutputPin> + 'b,
         cs: impl Peripheral<P = impl OutputPin> + 'b,
         spi: impl Peripheral<P = impl SpiAnyPins> + 'b,
-    ) -> anyhow::Result<Self> {
+    ) -> crate::Result<Self> {
+        if u32::from(freq) > panel.max_frequency() {
+            return Err(Error::SpiFrequency {
+                frequency: u32::from(freq),
+                max: panel.max_frequency(),
+            });
+        }
+
+        let max_transfer_size = match dma {
+            Dma::Disabled => None,
+            Dma::Channel1(size) | Dma::Channel2(size) | Dma::Auto(size) => Some(size),
+        };
+
+        // enough room to queue every chunk of a full frame at once
+        let queue_size = max_transfer_size.map_or(4, |size| {
+            let chunk_size = QueuedTransfer::chunk_size(size);
+            ((panel.frame_bytes() + chunk_size - 1) / chunk_size).max(4)
+        });
+
         let config = Config::new()
             .data_mode(MODE_0)
             .baudrate(freq)
             .bit_order(BitOrder::LsbFirst)
             .cs_active_high()
-            .queue_size(4);
+            .queue_size(queue_size);
 
         let driver_config: DriverConfig = DriverConfig {
-            dma: Dma::Disabled,
+            dma,
             intr_flags: IntrFlags::Level1.into(),
         };
 
         let driver = SpiDriver::new(spi, sclk, sdo, Option::<AnyIOPin>::None, &driver_config)?;
 
-        let device_driver = SpiDeviceDriver::new(driver, Some(cs), &config)?;
+        let device = SpiDeviceDriver::new(driver, Some(cs), &config)?;
+
+        let queue = match max_transfer_size {
+            Some(size) => Some((
+                DmaBuffer::new(panel.frame_bytes())?,
+                QueuedTransfer::new(device.device(), size),
+            )),
+            None => None,
+        };
+
+        let display = SharpSpiDisplay::new(
+            panel,
+            EspSpiDevice { device, queue },
+            vec![0x00; panel.frame_bytes()].into_boxed_slice(),
+        )?;
 
         Ok(Self {
-            vcom: 0x00,
-            device: device_driver,
+            display,
+            last_vcom_toggle: Instant::now(),
+            disp: None,
+            extmode: None,
+            power: None,
+            powered: true,
         })
     }
 
-    fn toggle_vcom(&mut self) {
-        self.vcom = if self.vcom != 0x00 {
-            0x00
-        } else {
-            SHARPMEM_CMD_VCOM
-        };
+    /// Drives the panel's DISP pin, turning the display on.
+    pub fn with_disp_pin(
+        mut self,
+        disp: impl Peripheral<P = impl OutputPin> + 'b,
+    ) -> crate::Result<Self> {
+        let mut disp = PinDriver::output(disp.into_ref().map_into::<AnyOutputPin>())?;
+        disp.set_high()?;
+        self.disp = Some(disp);
+        Ok(self)
+    }
+
+    /// Drives the panel's EXTMODE pin low, so VCOM follows the bit this driver sends with
+    /// every command rather than the EXTCOMIN pin.
+    pub fn with_extmode_pin(
+        mut self,
+        extmode: impl Peripheral<P = impl OutputPin> + 'b,
+    ) -> crate::Result<Self> {
+        let mut extmode = PinDriver::output(extmode.into_ref().map_into::<AnyOutputPin>())?;
+        extmode.set_low()?;
+        self.extmode = Some(extmode);
+        Ok(self)
+    }
+
+    /// Drives the enable of the panel's supply, turning it on.
+    pub fn with_power_pin(
+        mut self,
+        power: impl Peripheral<P = impl OutputPin> + 'b,
+    ) -> crate::Result<Self> {
+        let mut power = PinDriver::output(power.into_ref().map_into::<AnyOutputPin>())?;
+        power.set_high()?;
+        self.power = Some(power);
+        Ok(self)
+    }
+
+    /// Shows the contents of the panel's pixel memory again after `display_off`.
+    pub fn display_on(&mut self) -> crate::Result<()> {
+        match self.disp.as_mut() {
+            Some(disp) => disp.set_high().map_err(Error::from),
+            None => Err(Error::NoDispPin),
+        }
+    }
+
+    /// Blanks the glass while keeping the contents of the panel's pixel memory.
+    pub fn display_off(&mut self) -> crate::Result<()> {
+        match self.disp.as_mut() {
+            Some(disp) => disp.set_low().map_err(Error::from),
+            None => Err(Error::NoDispPin),
+        }
+    }
+
+    /// Blanks the display and cuts the panel's supply, following the datasheet's power
+    /// off sequence. A queued transfer is finished first, so the panel isn't cut off in
+    /// the middle of a command. The pixel memory is lost and has to be redrawn after
+    /// `power_up`.
+    pub fn power_down(&mut self) -> crate::Result<()> {
+        if self.power.is_none() {
+            return Err(Error::NoPowerPin);
+        }
+
+        self.flush()?;
+
+        if let Some(disp) = self.disp.as_mut() {
+            disp.set_low()?;
+        }
+
+        if let Some(power) = self.power.as_mut() {
+            power.set_low()?;
+        }
+
+        self.powered = false;
+
+        Ok(())
+    }
+
+    /// Powers the panel back on, clears its undefined pixel memory and turns the display on.
+    pub fn power_up(&mut self) -> crate::Result<()> {
+        match self.power.as_mut() {
+            Some(power) => power.set_high()?,
+            None => return Err(Error::NoPowerPin),
+        }
+
+        self.powered = true;
+        self.clear_display()?;
+
+        if let Some(disp) = self.disp.as_mut() {
+            disp.set_high()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn panel(&self) -> SharpPanel {
+        self.display.panel()
+    }
+
+    /// Notes that a command was sent, which inverted VCOM like every command does.
+    fn sent(&mut self, result: crate::Result<()>) -> crate::Result<()> {
+        self.last_vcom_toggle = Instant::now();
+
+        result
+    }
+
+    /// Sends a command carrying only the VCOM bit, inverting the panel's polarity without
+    /// touching its pixel memory.
+    pub fn send_vcom(&mut self) -> crate::Result<()> {
+        let result = self.display.send_vcom();
+
+        self.sent(result)
+    }
+
+    /// Whether the panel's supply is on, which it is unless `power_down` cut it.
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Time since VCOM was last inverted by any command.
+    pub fn since_vcom_toggle(&self) -> Duration {
+        self.last_vcom_toggle.elapsed()
     }
 }
 
 impl Display for SharpMemoryDisplay<'_> {
-    fn clear_display(&mut self) -> anyhow::Result<()> {
-        let command: [u8; 2] = [self.vcom | SHARPMEM_CMD_CLEAR_SCREEN, 0];
-        self.toggle_vcom();
-        self.device.write(&command).map_err(anyhow::Error::from)
-    }
-
-    fn refresh(&mut self, buffer: &Vec<Vec<u8>>) -> anyhow::Result<()> {
-        let command: u8 = self.vcom | SHARPMEM_CMD_WRITE_LINE;
-        let mut commands = buffer.iter().fold(vec![command], |mut acc, el| {
-            acc.push(((acc.len() - 1) / (el.len() + 2) + 1) as u8); // calculate line number
-            acc.extend(el.clone());
-            acc.push(0x00);
-            acc
-        });
+    fn width(&self) -> u16 {
+        self.display.width()
+    }
+
+    fn height(&self) -> u16 {
+        self.display.height()
+    }
+
+    fn clear_display(&mut self) -> crate::Result<()> {
+        let result = self.display.clear_display();
+
+        self.sent(result)
+    }
 
-        commands.push(0x00);
+    fn refresh(&mut self, buffer: FrameBufferView<'_>) -> crate::Result<()> {
+        let result = self.display.refresh(buffer);
 
-        self.toggle_vcom();
-        self.device.write(&commands).map_err(anyhow::Error::from)
+        self.sent(result)
     }
 
-    fn refresh_line(&mut self, line_num: u8, buffer: &[u8]) -> anyhow::Result<()> {
-        let command: u8 = self.vcom | SHARPMEM_CMD_WRITE_LINE;
-        let mut commands: Vec<u8> = vec![command, line_num + 1];
+    fn refresh_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        let result = self.display.refresh_line(line_num, buffer);
 
-        commands.extend(buffer);
-        commands.push(0x00);
-        commands.push(0x00);
+        self.sent(result)
+    }
+
+    fn refresh_lines(&mut self, lines: &[(u16, &[u8])]) -> crate::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.display.refresh_lines(lines);
+
+        self.sent(result)
+    }
+
+    fn refresh_dirty(&mut self, buffer: FrameBufferView<'_>, dirty: &[bool]) -> crate::Result<()> {
+        if !dirty.iter().any(|dirty| *dirty) {
+            return Ok(());
+        }
+
+        let result = self.display.refresh_dirty(buffer, dirty);
+
+        self.sent(result)
+    }
 
-        self.toggle_vcom();
-        self.device.write(&commands).map_err(anyhow::Error::from)
+    fn flush(&mut self) -> crate::Result<()> {
+        self.display.device_mut().wait()
     }
 }
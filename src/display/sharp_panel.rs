@@ -0,0 +1,146 @@
+/// How a panel expects the gate line address of each row to be sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineAddressing {
+    /// A mode byte, then an 8-bit address per row, used by panels of up to 255 rows.
+    EightBit,
+    /// 6 mode bits and a 10-bit address sharing 16 bits per row, used by taller panels
+    /// such as the 336x536 LS032B7DD02.
+    TenBit,
+}
+
+impl LineAddressing {
+    /// Largest zero based row index that can be addressed, gate addresses start at 1.
+    pub const fn max_line(&self) -> u16 {
+        match self {
+            LineAddressing::EightBit => 0xFF - 1,
+            LineAddressing::TenBit => 0x3FF - 1,
+        }
+    }
+
+    /// Bytes preceding the data of each row, carrying the mode or dummy bits and the address.
+    pub const fn header_bytes(&self) -> usize {
+        2
+    }
+
+    /// Dummy bytes ending the last row, followed by the ones ending the transfer.
+    pub const fn trailer_bytes(&self) -> usize {
+        match self {
+            LineAddressing::EightBit => 2,
+            LineAddressing::TenBit => 4,
+        }
+    }
+}
+
+/// Sharp memory LCD models, with the geometry and bus limits the driver needs.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SharpPanel {
+    /// 0.56" 64x64
+    LS006B7DH03,
+    /// 1.0" 128x128
+    LS010B7DH04,
+    /// 1.08" 160x68
+    LS011B7DH03,
+    /// 1.18" 184x38
+    LS012B7DD01,
+    /// 1.26" 128x128
+    LS013B7DH03,
+    /// 1.28" 144x168
+    LS013B7DH05,
+    /// 2.7" 400x240
+    LS027B7DH01,
+    /// 3.16" 336x536
+    LS032B7DD02,
+}
+
+/// Fails the build when a panel has more rows than its line addressing can reach.
+const _: () = {
+    let mut i = 0;
+
+    while i < SharpPanel::ALL.len() {
+        let panel = SharpPanel::ALL[i];
+        assert!(
+            panel.height() - 1 <= panel.addressing().max_line(),
+            "panel is too tall for its line addressing"
+        );
+        i += 1;
+    }
+};
+
+impl SharpPanel {
+    /// Every supported model.
+    pub const ALL: [SharpPanel; 8] = [
+        SharpPanel::LS006B7DH03,
+        SharpPanel::LS010B7DH04,
+        SharpPanel::LS011B7DH03,
+        SharpPanel::LS012B7DD01,
+        SharpPanel::LS013B7DH03,
+        SharpPanel::LS013B7DH05,
+        SharpPanel::LS027B7DH01,
+        SharpPanel::LS032B7DD02,
+    ];
+
+    pub const fn width(&self) -> u16 {
+        match self {
+            SharpPanel::LS006B7DH03 => 64,
+            SharpPanel::LS010B7DH04 => 128,
+            SharpPanel::LS011B7DH03 => 160,
+            SharpPanel::LS012B7DD01 => 184,
+            SharpPanel::LS013B7DH03 => 128,
+            SharpPanel::LS013B7DH05 => 144,
+            SharpPanel::LS027B7DH01 => 400,
+            SharpPanel::LS032B7DD02 => 336,
+        }
+    }
+
+    pub const fn height(&self) -> u16 {
+        match self {
+            SharpPanel::LS006B7DH03 => 64,
+            SharpPanel::LS010B7DH04 => 128,
+            SharpPanel::LS011B7DH03 => 68,
+            SharpPanel::LS012B7DD01 => 38,
+            SharpPanel::LS013B7DH03 => 128,
+            SharpPanel::LS013B7DH05 => 168,
+            SharpPanel::LS027B7DH01 => 240,
+            SharpPanel::LS032B7DD02 => 536,
+        }
+    }
+
+    /// Number of bytes holding a single row of pixels.
+    pub const fn line_bytes(&self) -> usize {
+        (self.width() as usize + 7) / 8
+    }
+
+    /// Length of a write command updating every row of the panel.
+    pub const fn frame_bytes(&self) -> usize {
+        self.command_bytes(self.height() as usize)
+    }
+
+    /// Length of a write command updating `rows` rows.
+    pub const fn command_bytes(&self, rows: usize) -> usize {
+        let addressing = self.addressing();
+        rows * (addressing.header_bytes() + self.line_bytes()) + addressing.trailer_bytes()
+    }
+
+    /// Number of rows a write command fitting in `capacity` bytes can update.
+    pub const fn rows_per_command(&self, capacity: usize) -> usize {
+        let addressing = self.addressing();
+        capacity.saturating_sub(addressing.trailer_bytes())
+            / (addressing.header_bytes() + self.line_bytes())
+    }
+
+    /// Highest SPI clock the panel is specified for, in Hz.
+    pub const fn max_frequency(&self) -> u32 {
+        match self {
+            SharpPanel::LS027B7DH01 | SharpPanel::LS032B7DD02 => 2_000_000,
+            _ => 1_000_000,
+        }
+    }
+
+    pub const fn addressing(&self) -> LineAddressing {
+        match self {
+            SharpPanel::LS032B7DD02 => LineAddressing::TenBit,
+            _ => LineAddressing::EightBit,
+        }
+    }
+}
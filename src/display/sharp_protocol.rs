@@ -0,0 +1,148 @@
+use crate::display::{LineAddressing, SharpPanel};
+use crate::Error;
+
+pub const SHARPMEM_CMD_WRITE_LINE: u8 = 0b00000001;
+pub const SHARPMEM_CMD_VCOM: u8 = 0b00000010;
+pub const SHARPMEM_CMD_CLEAR_SCREEN: u8 = 0b00000100;
+
+/// Length of a command carrying only a mode byte, the clear and VCOM commands.
+pub const SHARPMEM_MODE_COMMAND_BYTES: usize = 2;
+
+/// Encodes the commands of the Sharp memory LCD protocol, independently of the bus they
+/// are sent over.
+///
+/// The panel's VCOM has to be inverted regularly to keep DC off the liquid crystal, so the
+/// VCOM bit of every command encoded is the opposite of the one before it.
+#[derive(Clone, Debug)]
+pub struct SharpEncoder {
+    panel: SharpPanel,
+    vcom: u8,
+}
+
+impl SharpEncoder {
+    pub fn new(panel: SharpPanel) -> Self {
+        SharpEncoder { panel, vcom: 0x00 }
+    }
+
+    pub fn panel(&self) -> SharpPanel {
+        self.panel
+    }
+
+    /// Whether the VCOM bit of the next command is set.
+    pub fn vcom(&self) -> bool {
+        self.vcom != 0x00
+    }
+
+    /// Encodes a command writing `lines` into `out` and returns its length.
+    pub fn encode_lines<'l>(
+        &mut self,
+        out: &mut [u8],
+        lines: impl IntoIterator<Item = (u16, &'l [u8])>,
+    ) -> crate::Result<usize> {
+        let len = self
+            .panel
+            .encode_lines(out, self.vcom | SHARPMEM_CMD_WRITE_LINE, lines)?;
+        self.toggle_vcom();
+
+        Ok(len)
+    }
+
+    /// Encodes a command clearing the panel's pixel memory to white.
+    pub fn encode_clear(&mut self, out: &mut [u8]) -> crate::Result<usize> {
+        self.encode_mode(out, SHARPMEM_CMD_CLEAR_SCREEN)
+    }
+
+    /// Encodes a command carrying only the VCOM bit, inverting the panel's polarity without
+    /// touching its pixel memory.
+    pub fn encode_vcom(&mut self, out: &mut [u8]) -> crate::Result<usize> {
+        self.encode_mode(out, 0x00)
+    }
+
+    /// Encodes `mode` and the dummy bits following it.
+    fn encode_mode(&mut self, out: &mut [u8], mode: u8) -> crate::Result<usize> {
+        let capacity = out.len();
+
+        out.get_mut(..SHARPMEM_MODE_COMMAND_BYTES)
+            .ok_or(Error::TransmitBuffer { capacity })?
+            .copy_from_slice(&[self.vcom | mode, 0x00]);
+        self.toggle_vcom();
+
+        Ok(SHARPMEM_MODE_COMMAND_BYTES)
+    }
+
+    fn toggle_vcom(&mut self) {
+        self.vcom = if self.vcom != 0x00 {
+            0x00
+        } else {
+            SHARPMEM_CMD_VCOM
+        };
+    }
+}
+
+impl SharpPanel {
+    /// Encodes a write command for `lines` into `out` and returns its length.
+    ///
+    /// Every row is preceded by its address and the bits before it, which carry the mode
+    /// for the first row and are dummy bits separating the rows after that.
+    pub fn encode_lines<'l>(
+        &self,
+        out: &mut [u8],
+        mode: u8,
+        lines: impl IntoIterator<Item = (u16, &'l [u8])>,
+    ) -> crate::Result<usize> {
+        let addressing = self.addressing();
+        let capacity = out.len();
+        let row_bytes = addressing.header_bytes() + self.line_bytes();
+        let mut len = 0;
+        let mut header = mode;
+
+        for (line_num, buffer) in lines {
+            self.check_line(line_num, buffer)?;
+
+            let row = out
+                .get_mut(len..len + row_bytes)
+                .ok_or(Error::TransmitBuffer { capacity })?;
+            let address = line_num + 1;
+
+            match addressing {
+                LineAddressing::EightBit => {
+                    row[0] = header;
+                    row[1] = address as u8;
+                }
+                LineAddressing::TenBit => {
+                    row[0] = (header & 0b00111111) | ((address as u8 & 0b11) << 6);
+                    row[1] = (address >> 2) as u8;
+                }
+            }
+
+            row[addressing.header_bytes()..].copy_from_slice(buffer);
+            len += row_bytes;
+            header = 0x00;
+        }
+
+        out.get_mut(len..len + addressing.trailer_bytes())
+            .ok_or(Error::TransmitBuffer { capacity })?
+            .fill(0x00);
+
+        Ok(len + addressing.trailer_bytes())
+    }
+
+    fn check_line(&self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        if line_num >= self.height() {
+            return Err(Error::LineOutOfBounds {
+                line: line_num,
+                height: self.height(),
+            });
+        }
+
+        if buffer.len() != self.line_bytes() {
+            return Err(Error::LineLength {
+                line: line_num,
+                len: buffer.len(),
+                expected: self.line_bytes(),
+            });
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::spi::{Error, ErrorType, Operation, SpiBus, SpiDevice};
+
+use crate::display::{Display, SharpEncoder, SharpPanel};
+use crate::graphics::FrameBufferView;
+
+/// Sharp memory LCD on any `embedded-hal` SPI device.
+///
+/// The device has to be configured for SPI mode 0 with chip select active high, see
+/// [`ActiveHighCs`] for buses whose devices only drive it low. Commands are encoded into
+/// `buffer` before being sent, which can be an array of `panel.frame_bytes()` bytes. Shorter
+/// buffers work too, frames are then split into several write commands.
+pub struct SharpSpiDisplay<SPI, B> {
+    encoder: SharpEncoder,
+    spi: SPI,
+    buffer: B,
+    msb_first: bool,
+}
+
+impl<SPI, B> SharpSpiDisplay<SPI, B>
+where
+    SPI: SpiDevice,
+    B: AsMut<[u8]>,
+{
+    pub fn new(panel: SharpPanel, spi: SPI, mut buffer: B) -> crate::Result<Self> {
+        let capacity = buffer.as_mut().len();
+
+        if panel.rows_per_command(capacity) == 0 {
+            return Err(crate::Error::TransmitBufferTooSmall {
+                capacity,
+                needed: panel.command_bytes(1),
+            });
+        }
+
+        Ok(SharpSpiDisplay {
+            encoder: SharpEncoder::new(panel),
+            spi,
+            buffer,
+            msb_first: false,
+        })
+    }
+
+    /// Reverses the bits of every byte sent, for buses that can only shift out the most
+    /// significant bit first while the panel expects the least significant one.
+    pub fn msb_first(mut self) -> Self {
+        self.msb_first = true;
+        self
+    }
+
+    pub fn panel(&self) -> SharpPanel {
+        self.encoder.panel()
+    }
+
+    /// The SPI device, e.g. to wait for a transfer it left running.
+    pub fn device_mut(&mut self) -> &mut SPI {
+        &mut self.spi
+    }
+
+    pub fn release(self) -> (SPI, B) {
+        (self.spi, self.buffer)
+    }
+
+    /// Sends a command carrying only the VCOM bit, inverting the panel's polarity without
+    /// touching its pixel memory. Has to be called regularly while nothing is drawn.
+    pub fn send_vcom(&mut self) -> crate::Result<()> {
+        let len = self.encoder.encode_vcom(self.buffer.as_mut())?;
+        self.transmit(len)
+    }
+
+    /// Encodes `lines` into as many write commands as the transmit buffer requires.
+    fn write_lines<'l>(
+        &mut self,
+        lines: impl IntoIterator<Item = (u16, &'l [u8])>,
+    ) -> crate::Result<()> {
+        let rows_per_command = self.panel().rows_per_command(self.buffer.as_mut().len());
+        let mut lines = lines.into_iter().peekable();
+
+        while lines.peek().is_some() {
+            let len = self
+                .encoder
+                .encode_lines(self.buffer.as_mut(), lines.by_ref().take(rows_per_command))?;
+            self.transmit(len)?;
+        }
+
+        Ok(())
+    }
+
+    fn transmit(&mut self, len: usize) -> crate::Result<()> {
+        let command = &mut self.buffer.as_mut()[..len];
+
+        if self.msb_first {
+            command
+                .iter_mut()
+                .for_each(|byte| *byte = byte.reverse_bits());
+        }
+
+        self.spi
+            .write(command)
+            .map_err(|err| crate::Error::Spi(err.kind()))
+    }
+}
+
+impl<SPI, B> Display for SharpSpiDisplay<SPI, B>
+where
+    SPI: SpiDevice,
+    B: AsMut<[u8]>,
+{
+    fn width(&self) -> u16 {
+        self.panel().width()
+    }
+
+    fn height(&self) -> u16 {
+        self.panel().height()
+    }
+
+    fn clear_display(&mut self) -> crate::Result<()> {
+        let len = self.encoder.encode_clear(self.buffer.as_mut())?;
+        self.transmit(len)
+    }
+
+    fn refresh(&mut self, buffer: FrameBufferView<'_>) -> crate::Result<()> {
+        let panel = self.panel();
+
+        if buffer.width() != panel.width() || buffer.height() != panel.height() {
+            return Err(crate::Error::BufferSize {
+                width: buffer.width(),
+                height: buffer.height(),
+                display_width: panel.width(),
+                display_height: panel.height(),
+            });
+        }
+
+        self.write_lines(
+            buffer
+                .rows()
+                .enumerate()
+                .map(|(line_num, line)| (line_num as u16, line)),
+        )
+    }
+
+    fn refresh_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        self.write_lines([(line_num, buffer)])
+    }
+
+    fn refresh_lines(&mut self, lines: &[(u16, &[u8])]) -> crate::Result<()> {
+        self.write_lines(lines.iter().copied())
+    }
+
+    fn refresh_dirty(&mut self, buffer: FrameBufferView<'_>, dirty: &[bool]) -> crate::Result<()> {
+        self.write_lines(
+            buffer
+                .rows()
+                .zip(dirty)
+                .enumerate()
+                .filter(|(_, (_, dirty))| **dirty)
+                .map(|(line_num, (line, _))| (line_num as u16, line)),
+        )
+    }
+}
+
+/// Turns an SPI bus and a chip select pin into a device driving chip select high for the
+/// length of a transaction, as the Sharp panels expect, unlike most `SpiDevice`
+/// implementations.
+pub struct ActiveHighCs<BUS, CS, D> {
+    bus: BUS,
+    cs: CS,
+    delay: D,
+}
+
+impl<BUS, CS, D> ActiveHighCs<BUS, CS, D>
+where
+    BUS: SpiBus,
+    CS: OutputPin,
+    D: DelayNs,
+{
+    /// Chip select setup time before the first clock, the longest of the supported panels.
+    const CS_SETUP_NS: u32 = 3_000;
+    /// Chip select hold time after the last clock.
+    const CS_HOLD_NS: u32 = 1_000;
+
+    pub fn new(bus: BUS, mut cs: CS, delay: D) -> crate::Result<Self> {
+        cs.set_low()
+            .map_err(|err| crate::Error::ChipSelect(digital::Error::kind(&err)))?;
+
+        Ok(ActiveHighCs { bus, cs, delay })
+    }
+
+    pub fn release(self) -> (BUS, CS, D) {
+        (self.bus, self.cs, self.delay)
+    }
+
+    fn run(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), BUS::Error> {
+        for operation in operations {
+            match operation {
+                Operation::Read(words) => self.bus.read(words)?,
+                Operation::Write(words) => self.bus.write(words)?,
+                Operation::Transfer(read, write) => self.bus.transfer(read, write)?,
+                Operation::TransferInPlace(words) => self.bus.transfer_in_place(words)?,
+                Operation::DelayNs(ns) => {
+                    self.bus.flush()?;
+                    self.delay.delay_ns(*ns);
+                }
+            }
+        }
+
+        self.bus.flush()
+    }
+}
+
+/// Error of an [`ActiveHighCs`] transaction, from the bus or the chip select pin.
+#[derive(Debug)]
+pub enum ActiveHighCsError<BUS, CS> {
+    Spi(BUS),
+    ChipSelect(CS),
+}
+
+impl<BUS, CS> Error for ActiveHighCsError<BUS, CS>
+where
+    BUS: Error,
+    CS: core::fmt::Debug,
+{
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        match self {
+            ActiveHighCsError::Spi(err) => err.kind(),
+            ActiveHighCsError::ChipSelect(_) => embedded_hal::spi::ErrorKind::ChipSelectFault,
+        }
+    }
+}
+
+impl<BUS, CS, D> ErrorType for ActiveHighCs<BUS, CS, D>
+where
+    BUS: ErrorType,
+    CS: OutputPin,
+{
+    type Error = ActiveHighCsError<BUS::Error, CS::Error>;
+}
+
+impl<BUS, CS, D> SpiDevice for ActiveHighCs<BUS, CS, D>
+where
+    BUS: SpiBus,
+    CS: OutputPin,
+    D: DelayNs,
+{
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.cs.set_high().map_err(ActiveHighCsError::ChipSelect)?;
+        self.delay.delay_ns(Self::CS_SETUP_NS);
+
+        let result = self.run(operations).map_err(ActiveHighCsError::Spi);
+
+        self.delay.delay_ns(Self::CS_HOLD_NS);
+        self.cs.set_low().map_err(ActiveHighCsError::ChipSelect)?;
+
+        result
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::display::{Display, SharpMemoryDisplay};
+use crate::graphics::FrameBufferView;
+use crate::Error;
+
+/// Lowest and highest VCOM frequency the Sharp datasheets allow, in Hz.
+pub const VCOM_FREQUENCY_RANGE: std::ops::RangeInclusive<u32> = 1..=60;
+
+const VCOM_TASK_STACK_SIZE: usize = 4096;
+
+/// Owns a [`SharpMemoryDisplay`] and keeps inverting its VCOM from a background thread.
+///
+/// Every command sent to the panel already flips VCOM, but a screen that isn't redrawn
+/// would otherwise hold the same polarity indefinitely, building up a DC bias that damages
+/// the liquid crystal. The thread sends a bare VCOM command whenever no other command did so
+/// within one period, and none while the panel is powered down. `VcomTask` implements
+/// [`Display`] itself, so it can be handed to `MonoGraphics` in place of the display.
+pub struct VcomTask {
+    display: Arc<Mutex<SharpMemoryDisplay<'static>>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl VcomTask {
+    pub fn start(display: SharpMemoryDisplay<'static>, frequency: u32) -> crate::Result<Self> {
+        if !VCOM_FREQUENCY_RANGE.contains(&frequency) {
+            return Err(Error::VcomFrequency {
+                frequency,
+                range: VCOM_FREQUENCY_RANGE,
+            });
+        }
+
+        let display = Arc::new(Mutex::new(display));
+        let running = Arc::new(AtomicBool::new(true));
+        let period = Duration::from_secs(1) / frequency;
+
+        let thread = {
+            let display = display.clone();
+            let running = running.clone();
+
+            thread::Builder::new()
+                .name("vcom".into())
+                .stack_size(VCOM_TASK_STACK_SIZE)
+                .spawn(move || {
+                    while running.load(Ordering::Relaxed) {
+                        let wait = match display.lock() {
+                            Ok(mut display) => {
+                                let elapsed = display.since_vcom_toggle();
+
+                                if !display.is_powered() {
+                                    period
+                                } else if elapsed < period {
+                                    period - elapsed
+                                } else {
+                                    if let Err(err) = display.send_vcom() {
+                                        log::error!("Failed to toggle VCOM: {}", err);
+                                    }
+                                    period
+                                }
+                            }
+                            Err(_) => return,
+                        };
+
+                        thread::sleep(wait);
+                    }
+                })?
+        };
+
+        Ok(Self {
+            display,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Gives access to the display, e.g. to turn it off, while the thread keeps waiting. The
+    /// thread stops toggling VCOM while the display is powered down, and picks up again
+    /// once it is powered up.
+    pub fn lock(&self) -> crate::Result<MutexGuard<'_, SharpMemoryDisplay<'static>>> {
+        self.display.lock().map_err(|_| Error::PoisonedLock)
+    }
+}
+
+impl Drop for VcomTask {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Display for VcomTask {
+    fn width(&self) -> u16 {
+        self.display.lock().map_or(0, |display| display.width())
+    }
+
+    fn height(&self) -> u16 {
+        self.display.lock().map_or(0, |display| display.height())
+    }
+
+    fn clear_display(&mut self) -> crate::Result<()> {
+        self.lock()?.clear_display()
+    }
+
+    fn refresh(&mut self, buffer: FrameBufferView<'_>) -> crate::Result<()> {
+        self.lock()?.refresh(buffer)
+    }
+
+    fn refresh_line(&mut self, line_num: u16, buffer: &[u8]) -> crate::Result<()> {
+        self.lock()?.refresh_line(line_num, buffer)
+    }
+
+    fn refresh_lines(&mut self, lines: &[(u16, &[u8])]) -> crate::Result<()> {
+        self.lock()?.refresh_lines(lines)
+    }
+
+    fn refresh_dirty(&mut self, buffer: FrameBufferView<'_>, dirty: &[bool]) -> crate::Result<()> {
+        self.lock()?.refresh_dirty(buffer, dirty)
+    }
+
+    fn flush(&mut self) -> crate::Result<()> {
+        self.lock()?.flush()
+    }
+}
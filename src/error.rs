@@ -0,0 +1,299 @@
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::graphics::MAX_CLIP_DEPTH;
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Everything that can go wrong in this crate.
+///
+/// The errors of the drawing code and the Sharp encoding only carry plain values, so they
+/// work without an allocator. With `std` this implements `std::error::Error` and converts
+/// into `anyhow::Error` with `?`.
+///
+/// Variants wrapping another error only describe what failed and return the wrapped error
+/// from `source`, so a report that walks the chain names each cause once. The SPI and pin
+/// error kinds aren't errors of their own and are part of the message instead.
+#[derive(Debug)]
+pub enum Error {
+    /// Rows of `stride` bytes are too short for `width` pixels.
+    RowTooShort {
+        stride: usize,
+        width: u16,
+    },
+    /// Storage of `len` bytes was given for `height` rows of `stride` bytes.
+    StorageLength {
+        height: u16,
+        stride: usize,
+        len: usize,
+    },
+    /// A `width` x `height` buffer was given to a display of another size.
+    BufferSize {
+        width: u16,
+        height: u16,
+        display_width: u16,
+        display_height: u16,
+    },
+    /// `len` dirty flags were given for a canvas `height` rows tall.
+    DirtyLines {
+        len: usize,
+        height: u16,
+    },
+    /// `push_clip` was called with [`MAX_CLIP_DEPTH`] clip rectangles pushed already.
+    ClipStackFull,
+    /// `pop_clip` was called without a clip rectangle to pop.
+    ClipStackEmpty,
+    /// `len` bytes of storage for dithering error were given for an image that needs `needed`.
+    DitherStorage {
+        len: usize,
+        needed: usize,
+    },
+    /// A texture file of `len` bytes doesn't even hold a header.
+    TextureTooShort {
+        len: usize,
+    },
+    /// A texture file doesn't start with `TEXTURE_MAGIC` but with these bytes.
+    TextureMagic([u8; 4]),
+    TextureVersion(u8),
+    TextureBitOrder(u8),
+    TextureFlags(u16),
+    /// A texture file without the checksum flag has this in its checksum field instead of 0.
+    TextureChecksumField(u32),
+    /// The pixels of a texture wouldn't fit in memory.
+    TextureTooLarge {
+        height: u16,
+        stride: u32,
+    },
+    /// A texture file holds `len` bytes of pixels where its header announces `expected`.
+    TextureLength {
+        height: u16,
+        stride: u32,
+        expected: usize,
+        len: usize,
+    },
+    TextureChecksum {
+        actual: u32,
+        expected: u32,
+    },
+    /// Line `line` is beyond the `height` rows of the panel.
+    LineOutOfBounds {
+        line: u16,
+        height: u16,
+    },
+    /// Line `line` is `len` bytes long where the panel expects `expected`.
+    LineLength {
+        line: u16,
+        len: usize,
+        expected: usize,
+    },
+    /// A command doesn't fit the transmit buffer of `capacity` bytes.
+    TransmitBuffer {
+        capacity: usize,
+    },
+    /// A transmit buffer of `capacity` bytes can't hold a single row, which needs `needed`.
+    TransmitBufferTooSmall {
+        capacity: usize,
+        needed: usize,
+    },
+    Spi(embedded_hal::spi::ErrorKind),
+    ChipSelect(embedded_hal::digital::ErrorKind),
+    /// The SPI clock of `frequency` Hz is faster than the panel's `max`.
+    SpiFrequency {
+        frequency: u32,
+        max: u32,
+    },
+    NoDispPin,
+    NoPowerPin,
+    VcomFrequency {
+        frequency: u32,
+        range: RangeInclusive<u32>,
+    },
+    PoisonedLock,
+    DmaAlloc {
+        len: usize,
+    },
+    /// A transfer of `len` bytes doesn't fit the DMA buffer of `capacity` bytes.
+    DmaTransfer {
+        len: usize,
+        capacity: usize,
+    },
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Reading the file at `path` failed, `source` being an [`Error::Io`] or the texture
+    /// error of its contents.
+    #[cfg(feature = "std")]
+    File {
+        path: std::string::String,
+        source: std::boxed::Box<Error>,
+    },
+    #[cfg(feature = "esp-idf-svc")]
+    Esp(esp_idf_svc::sys::EspError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::RowTooShort { stride, width } => {
+                write!(f, "Rows of {} bytes can't hold {} pixels", stride, width)
+            }
+            Error::StorageLength {
+                height,
+                stride,
+                len,
+            } => write!(
+                f,
+                "{} rows of {} bytes need {} bytes, got {}",
+                height,
+                stride,
+                *height as usize * stride,
+                len
+            ),
+            Error::BufferSize {
+                width,
+                height,
+                display_width,
+                display_height,
+            } => write!(
+                f,
+                "Buffer is {}x{}, the display is {}x{}",
+                width, height, display_width, display_height
+            ),
+            Error::DirtyLines { len, height } => {
+                write!(f, "Got {} dirty flags for {} rows", len, height)
+            }
+            Error::ClipStackFull => write!(
+                f,
+                "Clip rectangles can't be nested more than {} deep",
+                MAX_CLIP_DEPTH
+            ),
+            Error::ClipStackEmpty => write!(f, "No clip rectangle to pop"),
+            Error::DitherStorage { len, needed } => write!(
+                f,
+                "Dithering the image needs {} bytes of error storage, got {}",
+                needed, len
+            ),
+            Error::TextureTooShort { len } => write!(
+                f,
+                "Texture file of {} bytes is too short for its header",
+                len
+            ),
+            Error::TextureMagic(magic) => write!(
+                f,
+                "Not a texture file, it starts with {:02x?} instead of \"SMTX\"",
+                magic
+            ),
+            Error::TextureVersion(version) => {
+                write!(f, "Texture file version {} isn't supported", version)
+            }
+            Error::TextureBitOrder(bit_order) => {
+                write!(f, "Unknown texture bit order {}", bit_order)
+            }
+            Error::TextureFlags(flags) => write!(f, "Unknown texture flags {:#06x}", flags),
+            Error::TextureChecksumField(field) => write!(
+                f,
+                "Texture without a checksum has {:08x} in its checksum field instead of 0",
+                field
+            ),
+            Error::TextureTooLarge { height, stride } => write!(
+                f,
+                "Texture of {} rows of {} bytes is too large",
+                height, stride
+            ),
+            Error::TextureLength {
+                height,
+                stride,
+                expected,
+                len,
+            } => write!(
+                f,
+                "Texture of {} rows of {} bytes needs {} bytes of pixels, the file has {}",
+                height, stride, expected, len
+            ),
+            Error::TextureChecksum { actual, expected } => write!(
+                f,
+                "Texture pixels have checksum {:08x}, the header says {:08x}",
+                actual, expected
+            ),
+            Error::LineOutOfBounds { line, height } => write!(
+                f,
+                "Line {} is out of bounds for the {} rows of the panel",
+                line, height
+            ),
+            Error::LineLength {
+                line,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Line {} is {} bytes long, the panel expects {}",
+                line, len, expected
+            ),
+            Error::TransmitBuffer { capacity } => write!(
+                f,
+                "Command doesn't fit the {} byte transmit buffer",
+                capacity
+            ),
+            Error::TransmitBufferTooSmall { capacity, needed } => write!(
+                f,
+                "A {} byte transmit buffer can't hold a row, it needs {}",
+                capacity, needed
+            ),
+            Error::Spi(kind) => write!(f, "SPI write failed: {:?}", kind),
+            Error::ChipSelect(kind) => write!(f, "Failed to set chip select: {:?}", kind),
+            Error::SpiFrequency { frequency, max } => write!(
+                f,
+                "The panel supports SPI clocks of up to {} Hz, got {} Hz",
+                max, frequency
+            ),
+            Error::NoDispPin => write!(f, "No DISP pin was configured"),
+            Error::NoPowerPin => write!(f, "No power pin was configured"),
+            Error::VcomFrequency { frequency, range } => write!(
+                f,
+                "VCOM frequency must be within {:?} Hz, got {} Hz",
+                range, frequency
+            ),
+            Error::PoisonedLock => write!(f, "Display lock was poisoned"),
+            Error::DmaAlloc { len } => {
+                write!(f, "Failed to allocate {} bytes of DMA capable memory", len)
+            }
+            Error::DmaTransfer { len, capacity } => write!(
+                f,
+                "Transfer of {} bytes doesn't fit the {} byte DMA buffer",
+                len, capacity
+            ),
+            #[cfg(feature = "std")]
+            Error::Io(_) => write!(f, "I/O operation failed"),
+            #[cfg(feature = "std")]
+            Error::File { path, .. } => write!(f, "Failed to read texture file {}", path),
+            #[cfg(feature = "esp-idf-svc")]
+            Error::Esp(_) => write!(f, "ESP-IDF call failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::File { source, .. } => Some(source.as_ref()),
+            #[cfg(feature = "esp-idf-svc")]
+            Error::Esp(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "esp-idf-svc")]
+impl From<esp_idf_svc::sys::EspError> for Error {
+    fn from(err: esp_idf_svc::sys::EspError) -> Self {
+        Error::Esp(err)
+    }
+}
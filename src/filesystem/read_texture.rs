@@ -1,8 +1,15 @@
+use crate::graphics::{HeapFrameBuffer, TextureFile};
+use crate::Error;
+
+#[cfg(feature = "esp-idf-svc")]
 use std::ffi::CString;
+#[cfg(feature = "esp-idf-svc")]
+use std::io;
 
-pub fn register_spiffs_partition(mount_point: &str, partition_name: &str) -> anyhow::Result<()> {
-    let base_path = CString::new(mount_point)?;
-    let partition = CString::new(partition_name)?;
+#[cfg(feature = "esp-idf-svc")]
+pub fn register_spiffs_partition(mount_point: &str, partition_name: &str) -> crate::Result<()> {
+    let base_path = CString::new(mount_point).map_err(io::Error::from)?;
+    let partition = CString::new(partition_name).map_err(io::Error::from)?;
 
     let conf = esp_idf_svc::sys::esp_vfs_spiffs_conf_t {
         base_path: base_path.as_ptr(),
@@ -18,12 +25,19 @@ pub fn register_spiffs_partition(mount_point: &str, partition_name: &str) -> any
     Ok(())
 }
 
-pub fn read_texture_to_buffer(file_path: &str) -> anyhow::Result<Vec<Vec<u8>>> {
-    let mut texture = std::fs::read(file_path).map_err(anyhow::Error::from)?;
-    let w = ((texture[0] as u16) << 8) | texture[1] as u16;
-    let _dims = texture.split_off(4);
-    Ok(texture
-        .chunks(w as usize)
-        .map(|el| el.to_vec())
-        .collect::<Vec<Vec<u8>>>())
+/// Reads a texture file, see [`crate::graphics::texture_file`] for its format.
+///
+/// Failures come as [`Error::File`] carrying the path, around the [`Error::Io`] or the
+/// texture error of the contents.
+pub fn read_texture_to_buffer(file_path: &str) -> crate::Result<HeapFrameBuffer> {
+    let in_file = |source: Error| Error::File {
+        path: file_path.into(),
+        source: Box::new(source),
+    };
+
+    let bytes = std::fs::read(file_path).map_err(|err| in_file(err.into()))?;
+
+    TextureFile::parse(&bytes)
+        .map(|texture| texture.to_frame_buffer())
+        .map_err(in_file)
 }
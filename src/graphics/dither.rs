@@ -0,0 +1,264 @@
+use crate::Error;
+
+#[cfg(feature = "alloc")]
+use super::{FrameBuffer, HeapFrameBuffer};
+
+/// How greyscale is turned into the black and white the panel can show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Thresholds each pixel against an 8x8 Bayer matrix anchored to the canvas, which is
+    /// fast and gives a regular cross-hatch that stays put when the image moves.
+    #[default]
+    Bayer,
+    /// Floyd–Steinberg error diffusion, which keeps the most detail and grey levels.
+    FloydSteinberg,
+    /// Atkinson error diffusion, which spreads only three quarters of the error and so gives
+    /// crisper, higher contrast pictures.
+    Atkinson,
+}
+
+/// Thresholds of the 8x8 Bayer matrix, in `0..64`.
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Rows of error kept below the one being dithered, plus that row itself.
+const ERROR_ROWS: usize = 3;
+
+impl Dither {
+    /// Where the error of a pixel goes, as `(dx, dy, weight)`, and what the weights add up
+    /// to, counting the error Atkinson drops.
+    fn kernel(self) -> (&'static [(i32, usize, i16)], i16) {
+        match self {
+            Dither::Bayer => (&[], 1),
+            Dither::FloydSteinberg => (&[(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)], 16),
+            Dither::Atkinson => (
+                &[
+                    (1, 0, 1),
+                    (2, 0, 1),
+                    (-1, 1, 1),
+                    (0, 1, 1),
+                    (1, 1, 1),
+                    (0, 2, 1),
+                ],
+                8,
+            ),
+        }
+    }
+
+    /// Length of the storage for the rows of error `MonoGraphics::draw_grey_image` needs to
+    /// dither an image `width` pixels wide, none for `Bayer`.
+    pub fn error_len(self, width: u16) -> usize {
+        if self.diffuses() {
+            ERROR_ROWS * (width as usize + 3)
+        } else {
+            0
+        }
+    }
+
+    /// Whether the outcome of a pixel depends on the pixels drawn before it.
+    pub(crate) fn diffuses(self) -> bool {
+        self != Dither::Bayer
+    }
+
+    /// The 8x8 tile of a flat `intensity` of grey, wrapping the error around its edges so
+    /// that it repeats seamlessly. Bit `n` of each row is column `n`, set bits being white.
+    pub(crate) fn tile(self, intensity: u8) -> [u8; 8] {
+        let mut rows = [0u8; 8];
+
+        if !self.diffuses() {
+            for (y, row) in rows.iter_mut().enumerate() {
+                for x in 0..8 {
+                    *row |= (bayer_white(intensity, x, y as i32) as u8) << x;
+                }
+            }
+
+            return rows;
+        }
+
+        let (kernel, total) = self.kernel();
+        let mut error = [[0i16; 8]; 8];
+
+        // the first pass gathers the error that wraps around from the bottom to the top
+        for y in (0..8).chain(0..8) {
+            for x in 0..8 {
+                let (white, e) = quantize(intensity as i16 + error[y][x]);
+                error[y][x] = 0;
+                rows[y] = rows[y] & !(1u8 << x) | (white as u8) << x;
+
+                for &(dx, dy, weight) in kernel {
+                    error[(y + dy) % 8][(x as i32 + dx).rem_euclid(8) as usize] +=
+                        e * weight / total;
+                }
+            }
+        }
+
+        rows
+    }
+}
+
+/// Whether a pixel of `intensity` at `(x, y)` on the canvas comes out white with ordered
+/// dithering.
+fn bayer_white(intensity: u8, x: i32, y: i32) -> bool {
+    let threshold = BAYER[(y & 7) as usize][(x & 7) as usize] as u32;
+    intensity as u32 * 64 > threshold * 255 + 127
+}
+
+/// Rounds a grey level with the error gathered so far to black or white, returning the
+/// colour and the error left over.
+fn quantize(level: i16) -> (bool, i16) {
+    let level = level.clamp(0, 255);
+
+    if level >= 128 {
+        (true, level - 255)
+    } else {
+        (false, level)
+    }
+}
+
+/// An 8-bit greyscale image, row after row, 0 being black and 255 white.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GreyImage<'a> {
+    width: u16,
+    height: u16,
+    stride: usize,
+    data: &'a [u8],
+}
+
+impl<'a> GreyImage<'a> {
+    /// Wraps `height` rows of `stride` bytes, of which the first `width` are pixels.
+    pub fn from_storage(
+        width: u16,
+        height: u16,
+        stride: usize,
+        data: &'a [u8],
+    ) -> crate::Result<Self> {
+        if stride < width as usize {
+            return Err(Error::RowTooShort { stride, width });
+        }
+
+        if data.len() != stride * height as usize {
+            return Err(Error::StorageLength {
+                height,
+                stride,
+                len: data.len(),
+            });
+        }
+
+        Ok(GreyImage {
+            width,
+            height,
+            stride,
+            data,
+        })
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn row(&self, y: u16) -> &'a [u8] {
+        let start = y as usize * self.stride;
+        &self.data[start..start + self.width as usize]
+    }
+
+    /// Dithers the whole image into a new frame buffer, as `MonoGraphics::draw_grey_image`
+    /// would draw it at the top left corner of the canvas, but however wide it is.
+    #[cfg(feature = "alloc")]
+    pub fn dither(&self, dither: Dither) -> crate::Result<HeapFrameBuffer> {
+        let mut buffer = FrameBuffer::new(self.width, self.height);
+        let errors = alloc::vec![0; dither.error_len(self.width)];
+        let mut ditherer = Ditherer::with_storage(dither, self.width, errors)?;
+
+        for y in 0..self.height {
+            let row = buffer.row_mut(y);
+
+            ditherer.row(self.row(y), 0, y as i32, |x, white| {
+                if !white {
+                    row[x as usize / 8] &= !(1 << (x % 8));
+                }
+            });
+        }
+
+        Ok(buffer)
+    }
+}
+
+/// Dithers the rows of a greyscale image one after the other, from the top.
+pub(crate) struct Ditherer<E> {
+    dither: Dither,
+    /// The error gathered for the current row and the ones below it, one row after the
+    /// other, with room for the kernels to reach a pixel past the left and two past the
+    /// right edge. The errors `quantize` leaves are within ±127 and no pixel gets more than
+    /// ~9/16 of that from the rows above and ~7/16 from its own row, so they fit an `i8`.
+    errors: E,
+    /// Length of each row of `errors`.
+    stride: usize,
+}
+
+impl<E: AsMut<[i8]>> Ditherer<E> {
+    /// Prepares to dither rows of `width` pixels, keeping their error in `errors`, which
+    /// has to hold at least [`Dither::error_len`] of them.
+    pub(crate) fn with_storage(dither: Dither, width: u16, mut errors: E) -> crate::Result<Self> {
+        let needed = dither.error_len(width);
+        let len = errors.as_mut().len();
+
+        if len < needed {
+            return Err(Error::DitherStorage { len, needed });
+        }
+
+        errors.as_mut()[..needed].fill(0);
+
+        Ok(Ditherer {
+            dither,
+            errors,
+            stride: width as usize + 3,
+        })
+    }
+
+    /// Dithers the next row, whose first pixel lands at `(x, y)` on the canvas, handing
+    /// the canvas column and colour of each pixel to `pixel`.
+    pub(crate) fn row(&mut self, source: &[u8], x: i32, y: i32, mut pixel: impl FnMut(i32, bool)) {
+        if !self.dither.diffuses() {
+            for (i, intensity) in source.iter().enumerate() {
+                let column = x + i as i32;
+                pixel(column, bayer_white(*intensity, column, y));
+            }
+
+            return;
+        }
+
+        let (kernel, total) = self.dither.kernel();
+        let stride = self.stride;
+        let errors = &mut self.errors.as_mut()[..ERROR_ROWS * stride];
+
+        for (i, intensity) in source.iter().enumerate() {
+            let (white, e) = quantize(*intensity as i16 + errors[i + 1] as i16);
+
+            for &(dx, dy, weight) in kernel {
+                let cell = &mut errors[dy * stride + (i as i32 + 1 + dx) as usize];
+                *cell = cell.saturating_add((e * weight / total) as i8);
+            }
+
+            pixel(x + i as i32, white);
+        }
+
+        errors.copy_within(stride.., 0);
+        errors[(ERROR_ROWS - 1) * stride..].fill(0);
+    }
+}
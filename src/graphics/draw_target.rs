@@ -0,0 +1,66 @@
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Dimensions, OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Pixel;
+
+use super::{Draw, MonoGraphics, SetPixel, Vect2D, BLACK, WHITE};
+
+/// `BinaryColor::On` is ink, drawn black on the white glass of the panel.
+fn to_mono(color: BinaryColor) -> bool {
+    match color {
+        BinaryColor::On => BLACK,
+        BinaryColor::Off => WHITE,
+    }
+}
+
+impl<B, L> OriginDimensions for MonoGraphics<'_, B, L> {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl<B, L> DrawTarget for MonoGraphics<'_, B, L>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+    L: AsRef<[bool]> + AsMut<[bool]>,
+{
+    type Color = BinaryColor;
+    type Error = crate::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> crate::Result<()>
+    where
+        I: IntoIterator<Item = Pixel<BinaryColor>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.set_pixel(Vect2D::new(point.x, point.y), to_mono(color))?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills whole bytes of every row at once, the same way as `draw_hline`.
+    fn fill_solid(&mut self, area: &Rectangle, color: BinaryColor) -> crate::Result<()> {
+        let area = area.intersection(&self.bounding_box());
+
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        let top_left = Vect2D::new(area.top_left.x, area.top_left.y);
+
+        for row in 0..area.size.height as i32 {
+            self.draw_hline(
+                Vect2D::new(top_left.x, top_left.y + row),
+                area.size.width,
+                to_mono(color),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: BinaryColor) -> crate::Result<()> {
+        Draw::clear(self, to_mono(color))
+    }
+}
@@ -0,0 +1,220 @@
+use core::ops::{Index, IndexMut};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// A 1-bit image stored row after row in one contiguous block of bytes.
+///
+/// Every row takes `stride` bytes, with the leftmost pixel in the least significant bit of
+/// the first byte and set bits being white, which is the layout the Sharp panels expect.
+/// The storage `S` can be anything that derefs to bytes, such as a [`HeapFrameBuffer`]'s
+/// `Vec<u8>`, a `&'static mut [u8]` or a `[u8; N]` array.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameBuffer<S> {
+    width: u16,
+    height: u16,
+    stride: usize,
+    data: S,
+}
+
+/// A frame buffer allocated on the heap with the size given at runtime.
+#[cfg(feature = "alloc")]
+pub type HeapFrameBuffer = FrameBuffer<Vec<u8>>;
+
+/// A borrowed frame buffer, as handed to a `Display`.
+pub type FrameBufferView<'a> = FrameBuffer<&'a [u8]>;
+
+/// A `W` x `H` frame buffer stored inline in `N` bytes, with its whole size in its type.
+/// Create it with [`FrameBuffer::new_inline`].
+pub type StaticFrameBuffer<const W: usize, const H: usize, const N: usize> =
+    FrameBuffer<InlinePixels<W, H, N>>;
+
+/// The `N` bytes of pixels of a [`StaticFrameBuffer`], which only `new_inline` creates
+/// after checking them against `W` and `H`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlinePixels<const W: usize, const H: usize, const N: usize>([u8; N]);
+
+impl<const W: usize, const H: usize, const N: usize> AsRef<[u8]> for InlinePixels<W, H, N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> AsMut<[u8]> for InlinePixels<W, H, N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
+/// Number of bytes a row of `width` pixels takes.
+pub const fn stride_for(width: u16) -> usize {
+    (width as usize + 7) / 8
+}
+
+/// Number of bytes a `width` x `height` frame buffer takes, for sizing arrays and statics.
+pub const fn frame_buffer_len(width: u16, height: u16) -> usize {
+    stride_for(width) * height as usize
+}
+
+/// Fails the build when `N` bytes don't hold exactly a `W` x `H` frame buffer.
+struct SizeCheck<const W: usize, const H: usize, const N: usize>;
+
+impl<const W: usize, const H: usize, const N: usize> SizeCheck<W, H, N> {
+    const OK: () = {
+        assert!(
+            W <= u16::MAX as usize && H <= u16::MAX as usize,
+            "frame buffer dimensions don't fit in u16"
+        );
+        assert!(
+            N == frame_buffer_len(W as u16, H as u16),
+            "frame buffer length doesn't match its dimensions"
+        );
+    };
+}
+
+#[cfg(feature = "alloc")]
+impl HeapFrameBuffer {
+    /// Allocates a white frame buffer.
+    pub fn new(width: u16, height: u16) -> Self {
+        let stride = stride_for(width);
+
+        FrameBuffer {
+            width,
+            height,
+            stride,
+            data: alloc::vec![0xFF; stride * height as usize],
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const N: usize> StaticFrameBuffer<W, H, N> {
+    /// Creates a white `W` x `H` buffer stored inline, so it can live on the stack or in a
+    /// `static` without a heap. `N` must be `frame_buffer_len(W, H)`, which is checked at
+    /// compile time.
+    ///
+    /// ```ignore
+    /// let buffer = StaticFrameBuffer::<400, 240, { frame_buffer_len(400, 240) }>::new_inline();
+    /// ```
+    pub const fn new_inline() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = SizeCheck::<W, H, N>::OK;
+
+        FrameBuffer {
+            width: W as u16,
+            height: H as u16,
+            stride: stride_for(W as u16),
+            data: InlinePixels([0xFF; N]),
+        }
+    }
+}
+
+impl<S> FrameBuffer<S> {
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    pub fn into_inner(self) -> S {
+        self.data
+    }
+}
+
+impl<S: AsRef<[u8]>> FrameBuffer<S> {
+    /// Wraps existing storage holding `height` rows of `stride` bytes.
+    pub fn from_storage(width: u16, height: u16, stride: usize, data: S) -> crate::Result<Self> {
+        if stride < stride_for(width) {
+            return Err(Error::RowTooShort { stride, width });
+        }
+
+        if data.as_ref().len() != stride * height as usize {
+            return Err(Error::StorageLength {
+                height,
+                stride,
+                len: data.as_ref().len(),
+            });
+        }
+
+        Ok(FrameBuffer {
+            width,
+            height,
+            stride,
+            data,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_ref()
+    }
+
+    pub fn row(&self, y: u16) -> &[u8] {
+        &self[y as usize]
+    }
+
+    pub fn rows(&self) -> core::slice::ChunksExact<'_, u8> {
+        self.as_bytes().chunks_exact(self.stride)
+    }
+
+    pub fn view(&self) -> FrameBufferView<'_> {
+        FrameBuffer {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            data: self.as_bytes(),
+        }
+    }
+
+    /// Copies the contents into a newly allocated frame buffer.
+    #[cfg(feature = "alloc")]
+    pub fn to_owned_buffer(&self) -> HeapFrameBuffer {
+        FrameBuffer {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            data: self.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl<S: AsRef<[u8]> + AsMut<[u8]>> FrameBuffer<S> {
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.data.as_mut()
+    }
+
+    pub fn row_mut(&mut self, y: u16) -> &mut [u8] {
+        &mut self[y as usize]
+    }
+
+    pub fn rows_mut(&mut self) -> core::slice::ChunksExactMut<'_, u8> {
+        let stride = self.stride;
+        self.as_bytes_mut().chunks_exact_mut(stride)
+    }
+
+    /// Sets every byte of the buffer to `byte`.
+    pub fn fill(&mut self, byte: u8) {
+        self.as_bytes_mut().fill(byte);
+    }
+}
+
+impl<S: AsRef<[u8]>> Index<usize> for FrameBuffer<S> {
+    type Output = [u8];
+
+    fn index(&self, y: usize) -> &[u8] {
+        &self.data.as_ref()[y * self.stride..(y + 1) * self.stride]
+    }
+}
+
+impl<S: AsRef<[u8]> + AsMut<[u8]>> IndexMut<usize> for FrameBuffer<S> {
+    fn index_mut(&mut self, y: usize) -> &mut [u8] {
+        let stride = self.stride;
+        &mut self.data.as_mut()[y * stride..(y + 1) * stride]
+    }
+}
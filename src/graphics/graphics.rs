@@ -1,37 +1,235 @@
-#[derive(Clone, Copy)]
+use super::FrameBufferView;
+
+/// A point on the canvas. Coordinates may lie outside of it, whatever falls off the
+/// canvas is clipped when drawing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Vect2D {
-    pub x: u16,
-    pub y: u16,
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Vect2D {
-    pub fn new(x: u16, y: u16) -> Self {
+    pub fn new(x: i32, y: i32) -> Self {
         Vect2D { x, y }
     }
 }
 
+/// An axis-aligned rectangle covering `min.x..max.x` and `min.y..max.y`, with `max` not
+/// included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub min: Vect2D,
+    pub max: Vect2D,
+}
+
+impl Rect {
+    pub fn new(min: Vect2D, max: Vect2D) -> Self {
+        Rect { min, max }
+    }
+
+    pub fn with_size(top_left: Vect2D, width: u32, height: u32) -> Self {
+        Rect {
+            min: top_left,
+            max: Vect2D::new(
+                top_left.x.saturating_add_unsigned(width),
+                top_left.y.saturating_add_unsigned(height),
+            ),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.max.x.saturating_sub(self.min.x).max(0) as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.max.y.saturating_sub(self.min.y).max(0) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x >= self.max.x || self.min.y >= self.max.y
+    }
+
+    pub fn contains(&self, c: Vect2D) -> bool {
+        c.x >= self.min.x && c.x < self.max.x && c.y >= self.min.y && c.y < self.max.y
+    }
+
+    /// The area covered by both rectangles, which is empty if they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        Rect {
+            min: Vect2D::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y)),
+            max: Vect2D::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y)),
+        }
+    }
+}
+
+/// Decides which parts of a self-intersecting polygon are inside, from how many times its
+/// edges wind around them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// Inside when enclosed by an odd number of edges, leaving holes where parts overlap.
+    EvenOdd,
+    /// Inside when the edges wind around at least once in either direction.
+    NonZero,
+}
+
+/// Shape of the ends of a thick line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    /// Cut square at the outer edge of the end pixels.
+    Butt,
+    /// Cut square half the width past the centre of the end pixels.
+    Square,
+    /// Rounded around the centre of the end pixels, with half the width as radius.
+    Round,
+}
+
+/// How `MonoGraphics` combines what is drawn with the pixels already in the buffer, with
+/// white being a set bit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawMode {
+    /// Pixels take the colour drawn.
+    #[default]
+    Replace,
+    /// Drawing white makes pixels white, drawing black leaves them as they are.
+    Or,
+    /// Drawing black makes pixels black, drawing white leaves them as they are.
+    And,
+    /// Drawing white inverts pixels, drawing black leaves them as they are, so drawing the
+    /// same twice restores what was there.
+    Xor,
+    /// Every pixel drawn is inverted, whatever the colour.
+    Invert,
+    /// Like `Replace`, but the background around text, the gaps of fill patterns and the
+    /// white pixels of textures are left as they are.
+    Transparent,
+}
+
+impl DrawMode {
+    /// Combines `value` into the bits of `dst` selected by `mask`, of which `foreground`
+    /// tells the ones that belong to the shape rather than to its background.
+    pub(crate) fn apply(self, dst: u8, mask: u8, value: u8, foreground: u8) -> u8 {
+        match self {
+            DrawMode::Replace => dst & !mask | value & mask,
+            DrawMode::Or => dst | value & mask,
+            DrawMode::And => dst & (value | !mask),
+            DrawMode::Xor => dst ^ value & mask,
+            DrawMode::Invert => dst ^ mask,
+            DrawMode::Transparent => {
+                let mask = mask & foreground;
+                dst & !mask | value & mask
+            }
+        }
+    }
+}
+
 pub trait SetPixel<T> {
-    fn set_pixel(&mut self, c: Vect2D, color: T) -> anyhow::Result<()>;
+    fn set_pixel(&mut self, c: Vect2D, color: T) -> crate::Result<()>;
 }
 
 pub trait Print<T> {
-    fn put_char(&mut self, c: &Vect2D, chr: char, color: T) -> anyhow::Result<()>;
+    fn put_char(&mut self, c: &Vect2D, chr: char, color: T) -> crate::Result<()>;
 }
 
 pub trait Draw<T>: SetPixel<T> {
-    fn clear(&mut self, color: T) -> anyhow::Result<()>;
+    fn clear(&mut self, color: T) -> crate::Result<()>;
+
+    fn draw_line(&mut self, c1: Vect2D, c2: Vect2D, color: T) -> crate::Result<()>;
+
+    fn draw_hline(&mut self, c: Vect2D, len: u32, color: T) -> crate::Result<()>;
+
+    fn draw_vline(&mut self, c: Vect2D, height: u32, color: T) -> crate::Result<()>;
+
+    /// Outline of the rectangle between `corner1` and `corner2`, both included, which may be
+    /// any two opposite corners.
+    fn draw_rectangle(&mut self, corner1: Vect2D, corner2: Vect2D, color: T) -> crate::Result<()>;
+
+    /// Fills the rectangle between `corner1` and `corner2`, given in any order, without its
+    /// rightmost column and bottom row, so `(0, 0)` and `(w, h)` fill `w` x `h` pixels.
+    fn fill_rectangle(&mut self, corner1: Vect2D, corner2: Vect2D, color: T) -> crate::Result<()>;
+
+    /// Line `width` pixels wide through the centres of pixels `c1` and `c2`, which `cap`
+    /// ends past both of them.
+    fn draw_thick_line(
+        &mut self,
+        c1: Vect2D,
+        c2: Vect2D,
+        width: u32,
+        cap: LineCap,
+        color: T,
+    ) -> crate::Result<()>;
+
+    /// Outline of the rectangle between `corner1` and `corner2`, both included like with
+    /// `draw_rectangle`, with the corners rounded off by quarter circles of `radius` pixels.
+    fn draw_round_rect(
+        &mut self,
+        corner1: Vect2D,
+        corner2: Vect2D,
+        radius: u32,
+        color: T,
+    ) -> crate::Result<()>;
+
+    /// Fills the rectangle from `corner1` up to `corner2`, which is left out like with
+    /// `fill_rectangle`, with the corners rounded off by quarter circles of `radius` pixels.
+    fn fill_round_rect(
+        &mut self,
+        corner1: Vect2D,
+        corner2: Vect2D,
+        radius: u32,
+        color: T,
+    ) -> crate::Result<()>;
+
+    /// Outline of the circle of `radius` pixels around `center`, `2 * radius + 1` pixels
+    /// across.
+    fn draw_circle(&mut self, center: Vect2D, radius: u32, color: T) -> crate::Result<()>;
+
+    fn fill_circle(&mut self, center: Vect2D, radius: u32, color: T) -> crate::Result<()>;
+
+    /// Outline of the axis-aligned ellipse around `center`, `2 * radius_x + 1` pixels wide
+    /// and `2 * radius_y + 1` pixels tall.
+    fn draw_ellipse(
+        &mut self,
+        center: Vect2D,
+        radius_x: u32,
+        radius_y: u32,
+        color: T,
+    ) -> crate::Result<()>;
+
+    fn fill_ellipse(
+        &mut self,
+        center: Vect2D,
+        radius_x: u32,
+        radius_y: u32,
+        color: T,
+    ) -> crate::Result<()>;
 
-    fn draw_line(&mut self, c1: Vect2D, c2: Vect2D, color: T) -> anyhow::Result<()>;
+    /// Part of the circle of `radius` pixels around `center` going clockwise from
+    /// `start_angle` to `end_angle`, in degrees from 3 o'clock. The stroke is `thickness`
+    /// pixels wide and grows inwards, a thickness above the radius gives a pie slice.
+    fn draw_arc(
+        &mut self,
+        center: Vect2D,
+        radius: u32,
+        start_angle: i32,
+        end_angle: i32,
+        thickness: u32,
+        color: T,
+    ) -> crate::Result<()>;
 
-    fn draw_hline(&mut self, c: Vect2D, len: u16, color: T) -> anyhow::Result<()>;
+    fn draw_triangle(&mut self, c1: Vect2D, c2: Vect2D, c3: Vect2D, color: T) -> crate::Result<()>;
 
-    fn draw_vline(&mut self, c: Vect2D, height: u16, color: T) -> anyhow::Result<()>;
+    fn fill_triangle(&mut self, c1: Vect2D, c2: Vect2D, c3: Vect2D, color: T) -> crate::Result<()>;
 
-    fn draw_rectangle(&mut self, corner1: Vect2D, corner2: Vect2D, color: T) -> anyhow::Result<()>;
+    /// Closed outline through `vertices`, the last one joined back to the first. Every
+    /// pixel of it is drawn once, even where edges meet or cross.
+    fn draw_polygon(&mut self, vertices: &[Vect2D], color: T) -> crate::Result<()>;
 
-    fn fill_rectangle(&mut self, corner1: Vect2D, corner2: Vect2D, color: T) -> anyhow::Result<()>;
+    /// Fills the pixels whose centre lies inside the polygon, with the vertices on the
+    /// corners of pixels. Like `fill_rectangle` leaves out `corner2`, the right and bottom
+    /// edges are left out, so polygons sharing an edge don't overlap.
+    fn fill_polygon(&mut self, vertices: &[Vect2D], rule: FillRule, color: T) -> crate::Result<()>;
 
-    fn draw_texture(&mut self, corner: Vect2D, texture: &Vec<Vec<u8>>) -> anyhow::Result<()>;
+    fn draw_texture(&mut self, corner: Vect2D, texture: FrameBufferView<'_>) -> crate::Result<()>;
 
-    fn draw_texture_from_flash(&mut self, corner: Vect2D, path: &str) -> anyhow::Result<()>;
+    #[cfg(feature = "std")]
+    fn draw_texture_from_flash(&mut self, corner: Vect2D, path: &str) -> crate::Result<()>;
 }
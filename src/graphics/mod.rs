@@ -1,8 +1,20 @@
+pub mod dither;
+#[cfg(feature = "embedded-graphics")]
+pub mod draw_target;
+pub mod frame_buffer;
 mod glcdfont;
+#[allow(clippy::module_inception)]
 pub mod graphics;
 pub mod mono_graphics;
+pub mod pattern;
 pub mod printer;
+mod shapes;
+pub mod texture_file;
 
+pub use dither::*;
+pub use frame_buffer::*;
 pub use graphics::*;
 pub use mono_graphics::*;
+pub use pattern::*;
 pub use printer::*;
+pub use texture_file::*;
@@ -1,186 +1,1089 @@
-use std::{borrow::BorrowMut, mem::swap};
+use core::borrow::BorrowMut;
 
-use anyhow::anyhow;
-use esp_idf_svc::sys::abs;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 use crate::display::Display;
+use crate::Error;
 
+use super::dither::Ditherer;
 use super::glcdfont::GLCD_FONT;
-use super::{Draw, Print, SetPixel, Vect2D};
+use super::shapes::{
+    stroke_outline, LineSteps, PolygonSpans, RingRows, Sector, MAX_RADIUS, MAX_STROKE_VERTICES,
+    SUBPIXEL,
+};
+use super::{
+    Dither, Draw, DrawMode, FillRule, FrameBuffer, FrameBufferView, GreyImage, Ink, InlinePixels,
+    LineCap, Pattern, Print, Rect, SetPixel, StaticFrameBuffer, Vect2D,
+};
 
 pub const WHITE: bool = true;
 pub const BLACK: bool = false;
 
 const SET: [u8; 8] = [1, 2, 4, 8, 16, 32, 64, 128];
-const CLR: [u8; 8] = [!1, !2, !4, !8, !16, !32, !64, !128];
-const SHO: [u8; 7] = [
-    0b01111111, 0b00111111, 0b00011111, 0b00001111, 0b00000111, 0b00000011, 0b00000001,
-];
-const CAP: [u8; 7] = [
-    !0b01111111,
-    !0b00111111,
-    !0b00011111,
-    !0b00001111,
-    !0b00000111,
-    !0b00000011,
-    !0b00000001,
+
+/// Number of clip rectangles `push_clip` can nest.
+pub const MAX_CLIP_DEPTH: usize = 8;
+
+/// Number of row spans `flood_fill` keeps track of, 8 bytes each on the stack. Areas too
+/// ragged for that are finished a pixel at a time instead, `flood_fill_with_storage` takes
+/// more room to keep that from happening.
+pub const FLOOD_FILL_SPANS: usize = 64;
+
+/// Steps `flood_fill` may always take walking round a pixel to tell whether the area stays
+/// connected without it, when it fills an area a pixel at a time.
+const NEARBY_STEPS: u32 = 16;
+
+/// Steps of such walks round a pixel that filling one pays for.
+const FILL_CREDIT: u32 = 8;
+
+/// Offsets of the pixels around one, clockwise from the one on its right, so that the
+/// horizontal and vertical neighbours are at even indices.
+const AROUND: [(i32, i32); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
 ];
 
-pub struct MonoGraphics<'a> {
+/// When more than this fraction of rows is dirty, `draw` sends the whole buffer instead of
+/// picking out the dirty rows.
+const FULL_REFRESH_RATIO: (usize, usize) = (1, 2);
+
+/// 1-bit graphics drawn into a frame buffer and sent to a `Display` on `draw`.
+///
+/// The frame buffer `B` and the dirty row flags `L` can live anywhere:
+/// [`HeapMonoGraphics`] allocates both on the heap with the size given at runtime,
+/// [`StaticMonoGraphics`] keeps both inline with the size fixed at compile time, and
+/// [`MonoGraphics::with_storage`] accepts any other memory, such as a `&'static mut [u8]`.
+///
+/// Everything drawn is clipped to the canvas and to the rectangle set with
+/// [`MonoGraphics::set_clip`] or [`MonoGraphics::push_clip`], so shapes may start
+/// off-screen, and combined with what is already there according to the
+/// [`DrawMode`] set with [`MonoGraphics::set_draw_mode`].
+pub struct MonoGraphics<'a, B, L> {
     pub display: &'a mut (dyn Display + 'a),
-    pub buffer: Vec<Vec<u8>>,
+    pub buffer: FrameBuffer<B>,
     pub width: u16,
     pub height: u16,
+    dirty_lines: L,
+    clip: Rect,
+    clip_stack: [Rect; MAX_CLIP_DEPTH],
+    clip_depth: usize,
+    fill_pattern: Pattern,
+    draw_mode: DrawMode,
 }
 
-impl<'a> MonoGraphics<'a> {
+/// `MonoGraphics` whose frame buffer and dirty flags are allocated on the heap with the
+/// size given at runtime. Create it with [`MonoGraphics::new`] or
+/// [`MonoGraphics::from_display`].
+#[cfg(feature = "alloc")]
+pub type HeapMonoGraphics<'a> = MonoGraphics<'a, Vec<u8>, Vec<bool>>;
+
+/// `MonoGraphics` for a `W` x `H` canvas whose frame buffer of `N` bytes and dirty flags
+/// are stored inline, for builds without a heap. Create it with
+/// [`MonoGraphics::new_static`], which checks `N` against `W` and `H` at compile time.
+pub type StaticMonoGraphics<'a, const W: usize, const H: usize, const N: usize> =
+    MonoGraphics<'a, InlinePixels<W, H, N>, [bool; H]>;
+
+#[cfg(feature = "alloc")]
+impl<'a> HeapMonoGraphics<'a> {
     pub fn new(display: &'a mut dyn Display, width: u16, height: u16) -> Self {
         MonoGraphics {
-            display: display,
-            buffer: vec![vec![0xFF; (width / 8) as usize]; height as usize],
-            width: width,
-            height: height,
+            display,
+            buffer: FrameBuffer::new(width, height),
+            width,
+            height,
+            dirty_lines: alloc::vec![true; height as usize],
+            clip: Rect::with_size(Vect2D::new(0, 0), width as u32, height as u32),
+            clip_stack: [Rect::default(); MAX_CLIP_DEPTH],
+            clip_depth: 0,
+            fill_pattern: Pattern::SOLID,
+            draw_mode: DrawMode::Replace,
         }
     }
 
-    pub fn clear_display(&mut self) -> anyhow::Result<()> {
-        self.display.clear_display()
+    /// Creates a buffer matching the dimensions reported by `display`.
+    pub fn from_display(display: &'a mut dyn Display) -> Self {
+        let (width, height) = (display.width(), display.height());
+        Self::new(display, width, height)
     }
+}
 
-    pub fn draw(&mut self) -> anyhow::Result<()> {
-        self.display.borrow_mut().refresh(&self.buffer)
+impl<'a, const W: usize, const H: usize, const N: usize> StaticMonoGraphics<'a, W, H, N> {
+    /// Creates a white `W` x `H` canvas, failing the build unless `N` is
+    /// `frame_buffer_len(W, H)` and returning an error if `display` has another size.
+    ///
+    /// ```ignore
+    /// let mut graphics: StaticMonoGraphics<400, 240, { frame_buffer_len(400, 240) }> =
+    ///     MonoGraphics::new_static(&mut display)?;
+    /// ```
+    pub fn new_static(display: &'a mut dyn Display) -> crate::Result<Self> {
+        Self::with_storage(display, StaticFrameBuffer::new_inline(), [true; H])
     }
 }
 
-impl SetPixel<bool> for MonoGraphics<'_> {
-    fn set_pixel(&mut self, c: Vect2D, color: bool) -> anyhow::Result<()> {
-        let left: u8 = (c.x % 8) as u8;
-        let whole: u16 = (c.x - left as u16) / 8;
+impl<'a, B, L> MonoGraphics<'a, B, L>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+    L: AsRef<[bool]> + AsMut<[bool]>,
+{
+    /// Draws into `buffer`, which must match the size of `display`, tracking modified rows
+    /// in `dirty_lines`, which must have a flag for each row.
+    pub fn with_storage(
+        display: &'a mut dyn Display,
+        buffer: FrameBuffer<B>,
+        mut dirty_lines: L,
+    ) -> crate::Result<Self> {
+        if (buffer.width(), buffer.height()) != (display.width(), display.height()) {
+            return Err(Error::BufferSize {
+                width: buffer.width(),
+                height: buffer.height(),
+                display_width: display.width(),
+                display_height: display.height(),
+            });
+        }
 
-        if color {
-            self.buffer[c.y as usize][whole as usize] |= SET[left as usize];
-        } else {
-            self.buffer[c.y as usize][whole as usize] &= CLR[left as usize];
+        if dirty_lines.as_ref().len() != buffer.height() as usize {
+            return Err(Error::DirtyLines {
+                len: dirty_lines.as_ref().len(),
+                height: buffer.height(),
+            });
         }
 
+        dirty_lines.as_mut().fill(true);
+
+        Ok(MonoGraphics {
+            display,
+            width: buffer.width(),
+            height: buffer.height(),
+            clip: Rect::with_size(
+                Vect2D::new(0, 0),
+                buffer.width() as u32,
+                buffer.height() as u32,
+            ),
+            clip_stack: [Rect::default(); MAX_CLIP_DEPTH],
+            clip_depth: 0,
+            fill_pattern: Pattern::SOLID,
+            draw_mode: DrawMode::Replace,
+            buffer,
+            dirty_lines,
+        })
+    }
+
+    /// Confines drawing to `clip`, on top of the bounds of the canvas, replacing the current
+    /// clip rectangle.
+    pub fn set_clip(&mut self, clip: Rect) {
+        self.clip = clip.intersect(&self.bounds());
+    }
+
+    /// Allows drawing anywhere on the canvas again and forgets every pushed clip rectangle.
+    pub fn reset_clip(&mut self) {
+        self.clip = self.bounds();
+        self.clip_depth = 0;
+    }
+
+    /// Confines drawing to the part of `clip` inside the current clip rectangle, until the
+    /// matching `pop_clip`. Can be nested up to [`MAX_CLIP_DEPTH`] times.
+    pub fn push_clip(&mut self, clip: Rect) -> crate::Result<()> {
+        if self.clip_depth == MAX_CLIP_DEPTH {
+            return Err(Error::ClipStackFull);
+        }
+
+        self.clip_stack[self.clip_depth] = self.clip;
+        self.clip_depth += 1;
+        self.clip = clip.intersect(&self.clip);
+
         Ok(())
     }
-}
 
-impl Draw<bool> for MonoGraphics<'_> {
-    fn clear(&mut self, color: bool) -> anyhow::Result<()> {
-        let line_color = if color { 0xFF } else { 0x00 };
+    /// Restores the clip rectangle in effect before the last `push_clip`.
+    pub fn pop_clip(&mut self) -> crate::Result<()> {
+        if self.clip_depth == 0 {
+            return Err(Error::ClipStackEmpty);
+        }
+
+        self.clip_depth -= 1;
+        self.clip = self.clip_stack[self.clip_depth];
 
-        for i in 0..self.buffer.len() {
-            self.buffer[i].fill(line_color);
+        Ok(())
+    }
+
+    /// The area drawing is currently confined to.
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    /// Paints the inside of filled shapes with `pattern`, their outlines and lines stay
+    /// solid.
+    pub fn set_fill_pattern(&mut self, pattern: Pattern) {
+        self.fill_pattern = pattern;
+    }
+
+    pub fn fill_pattern(&self) -> Pattern {
+        self.fill_pattern
+    }
+
+    /// Combines everything drawn from now on, shapes, text and textures alike, with the
+    /// pixels already in the buffer according to `mode`.
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self.draw_mode = mode;
+    }
+
+    pub fn draw_mode(&self) -> DrawMode {
+        self.draw_mode
+    }
+
+    /// Draws an 8-bit greyscale image with its top left corner at `corner`, dithered to
+    /// black and white with `dither`. Like with textures, the black pixels are the
+    /// foreground for `DrawMode::Transparent`.
+    ///
+    /// Error diffusion starts from the top left of the image whatever part of it is visible,
+    /// so it looks the same when clipped. It keeps its rows of error in `errors`, which has
+    /// to hold at least [`Dither::error_len`] of them for the width of the image.
+    pub fn draw_grey_image(
+        &mut self,
+        corner: Vect2D,
+        image: GreyImage<'_>,
+        dither: Dither,
+        errors: &mut [i8],
+    ) -> crate::Result<()> {
+        let mut ditherer = Ditherer::with_storage(dither, image.width(), errors)?;
+        let visible = Rect::with_size(corner, image.width() as u32, image.height() as u32)
+            .intersect(&self.clip);
+
+        if visible.is_empty() {
+            return Ok(());
+        }
+
+        let top = if dither.diffuses() {
+            corner.y
+        } else {
+            visible.min.y
+        };
+
+        for y in top..visible.max.y {
+            let is_visible = y >= visible.min.y;
+
+            ditherer.row(image.row((y - corner.y) as u16), corner.x, y, |x, white| {
+                if is_visible && x >= visible.min.x && x < visible.max.x {
+                    self.put_pixel(x as usize, y as usize, white, !white);
+                }
+            });
+
+            if is_visible {
+                self.mark_dirty(y as u16);
+            }
         }
 
         Ok(())
     }
 
-    fn draw_line(&mut self, mut c1: Vect2D, mut c2: Vect2D, color: bool) -> anyhow::Result<()> {
-        if c1.x >= self.width || c1.y >= self.height || c2.x >= self.width || c2.y >= self.height {
-            return Err(anyhow::anyhow!("Values out of bounds"));
+    /// Fills the area of the pixel at `seed` and every pixel of the same colour connected
+    /// to it horizontally or vertically with `color`, up to the clip rectangle.
+    ///
+    /// The fill is always solid and replaces the pixels, whatever the fill pattern and draw
+    /// mode, as pixels left in another colour couldn't be told apart from the border of the
+    /// area. It keeps track of at most [`FLOOD_FILL_SPANS`] row spans still to be looked at,
+    /// taken in the order they were found so that the fill spreads out evenly and few of them
+    /// are waiting at a time. When an area is too ragged even for that it walks the rest of it
+    /// pixel by pixel, which takes longer but no more memory.
+    pub fn flood_fill(&mut self, seed: Vect2D, color: bool) -> crate::Result<()> {
+        self.flood_fill_with_storage(
+            seed,
+            color,
+            &mut [FloodFillSpan::default(); FLOOD_FILL_SPANS],
+        )
+    }
+
+    /// Same as [`MonoGraphics::flood_fill`], keeping track of as many row spans as `spans`
+    /// holds, so that the caller decides how much memory ragged areas may take before they
+    /// are walked pixel by pixel.
+    pub fn flood_fill_with_storage(
+        &mut self,
+        seed: Vect2D,
+        color: bool,
+        spans: &mut [FloodFillSpan],
+    ) -> crate::Result<()> {
+        if !self.clip.contains(seed) || self.pixel(seed.x as usize, seed.y as usize) == color {
+            return Ok(());
         }
 
-        let steep = unsafe { abs((c2.y - c1.y).into()) > abs((c2.x - c1.x).into()) };
+        let mode = core::mem::replace(&mut self.draw_mode, DrawMode::Replace);
+        self.fill_area(seed, color, SpanQueue::new(spans, self.clip));
+        self.draw_mode = mode;
+
+        Ok(())
+    }
+
+    /// The part of `flood_fill` that runs in `DrawMode::Replace`.
+    fn fill_area(&mut self, seed: Vect2D, color: bool, mut spans: SpanQueue) {
+        let clip = self.clip;
+        let ink = Pattern::SOLID.ink(color);
+
+        // the seed row is looked at as if found from the row below, then the row below is
+        // looked at as if found from the seed
+        self.look_at(&mut spans, seed.y, seed.x, seed.x, 1, color);
+        self.look_at(&mut spans, seed.y + 1, seed.x, seed.x, -1, color);
+
+        while let Some((y, x0, x1, dy)) = spans.pop() {
+            // `x0..=x1` of row `y - dy` is filled, the area goes on where row `y` touches it
+            let is_area = |graphics: &Self, x: i32| graphics.pixel(x as usize, y as usize) != color;
+
+            let mut x = x0;
+
+            while x <= x1 && !is_area(self, x) {
+                x += 1;
+            }
+
+            if x > x1 {
+                continue;
+            }
+
+            let mut start = x;
+
+            while start > clip.min.x && is_area(self, start - 1) {
+                start -= 1;
+            }
+
+            while x < clip.max.x && is_area(self, x) {
+                x += 1;
+            }
+
+            self.span(Vect2D::new(start, y), (x - start) as u32, ink);
+
+            // only the first run touching the span is followed right away, the rest of the
+            // span waits its turn again, so that a ragged row takes one span rather than one
+            // for every run
+            if x < x1 {
+                self.look_at(&mut spans, y - dy as i32, x + 1, x1, dy, color);
+            }
+
+            // the run spills past the ends of the span it was found from, look back
+            if start < x0 {
+                self.look_at(&mut spans, y, start, x0 - 1, -dy, color);
+            }
+
+            if x > x1 + 1 {
+                self.look_at(&mut spans, y, x1 + 1, x - 1, -dy, color);
+            }
+
+            self.look_at(&mut spans, y, start, x - 1, dy, color);
+        }
+    }
 
-        if steep {
-            swap(&mut c1.x, &mut c1.y);
-            swap(&mut c2.x, &mut c2.y);
+    /// Remembers to look at the row next to `x0..=x1` of row `y` in direction `dy`, or fills
+    /// the area it leads to right away when there's no room left to remember it.
+    fn look_at(&mut self, spans: &mut SpanQueue, y: i32, x0: i32, x1: i32, dy: i8, color: bool) {
+        if spans.push(y, x0, x1, dy) {
+            return;
         }
 
-        if c1.x > c2.x {
-            swap(&mut c1, &mut c2);
+        for x in x0..=x1 {
+            let c = Vect2D::new(x, y + dy as i32);
+
+            if self.is_area(c, color) {
+                self.fill_piece(c, color);
+            }
         }
+    }
+
+    /// Whether `c` is inside the clip rectangle and yet to be flood filled with `color`.
+    fn is_area(&self, c: Vect2D, color: bool) -> bool {
+        self.clip.contains(c) && self.pixel(c.x as usize, c.y as usize) != color
+    }
+
+    /// Fills the part of the area connected to `start` remembering nothing but where it is.
+    ///
+    /// It walks the area a pixel at a time and only fills a pixel when the pixels around it
+    /// stay connected without it, so the rest of the area is always reachable from the next
+    /// one. The walk follows the edge of the area, and whether the neighbours of a pixel meet
+    /// without it is found by walking round the edge from each of them towards the other.
+    /// Those walks are paid for by the main one: it may always take a few steps round a
+    /// pixel, and on top of that as many as it earned by the steps it took and the pixels it
+    /// filled since, so that the time the whole fill takes stays in proportion to the length
+    /// of the walk. A pixel next to a small hole is filled right away, one next to a large
+    /// hole once the walk has gone far enough to pay for going round it.
+    ///
+    /// Only when it comes round to where it was without filling anything, going round a
+    /// loop, does it look as far as it takes. A pixel holding the area together is left for
+    /// later and the walk moves on to a part that doesn't lead back to where it came from,
+    /// which has to end at a pixel it can fill.
+    fn fill_piece(&mut self, start: Vect2D, color: bool) {
+        let ink = Pattern::SOLID.ink(color);
+        let mut at = start;
+        let mut heading = 0;
+        let mut lap = Lap::new(at, heading);
+        let mut from = None;
+        let mut credit = 0u32;
+
+        loop {
+            let (groups, len, from_group) = self.neighbour_groups(at, from, color);
+
+            if len == 0 {
+                self.span(at, 1, ink);
+                return;
+            }
+
+            let careful = lap.is_closed();
+            let budget = if careful {
+                u32::MAX
+            } else {
+                NEARBY_STEPS.saturating_add(credit)
+            };
+            let mut steps = budget;
+
+            // whether each group is still connected to the first one without `at`, if found
+            // out within the steps there are
+            let mut joined = [Some(true); 4];
 
-        let dx = c2.x - c1.x;
-        let dy = unsafe { abs((c2.y - c1.y).into()) } as u16;
-        let mut err: i32 = (dx / 2).into();
+            for i in 1..len {
+                joined[i] = self.connected_without(at, groups[0], groups[i], color, &mut steps);
 
-        while c1.x < c2.x {
-            c1.x += 1;
+                if joined[i] != Some(true) && !careful {
+                    break;
+                }
+            }
 
-            if steep {
-                self.set_pixel(Vect2D { x: c1.y, y: c1.x }, color)?;
+            credit = credit.saturating_sub(budget - steps);
+
+            if joined[..len].iter().all(|joined| *joined == Some(true)) {
+                self.span(at, 1, ink);
+                (at, heading) = self.follow_edge(at, heading, color);
+                lap = Lap::new(at, heading);
+                from = None;
+                credit = credit.saturating_add(FILL_CREDIT);
+            } else if !careful {
+                (at, heading) = self.follow_edge(at, heading, color);
+                lap.step(at, heading);
+                credit = credit.saturating_add(1);
             } else {
-                self.set_pixel(Vect2D { x: c1.x, y: c1.y }, color)?;
+                // `at` holds the area together, carry on into a part it didn't come from
+                let next = match from_group {
+                    Some(group) if joined[group] == Some(false) => 0,
+                    _ => (1..len)
+                        .find(|i| joined[*i] == Some(false))
+                        .unwrap_or_default(),
+                };
+
+                from = Some(at);
+                at = groups[next];
             }
+        }
+    }
 
-            err -= dy as i32;
+    /// The step from `at` along the edge of the area keeping the outside on the right, for
+    /// a walk going in the direction of `AROUND[heading]`.
+    fn follow_edge(&self, at: Vect2D, heading: usize, color: bool) -> (Vect2D, usize) {
+        for turn in [2, 0, 6, 4] {
+            let heading = (heading + turn) % 8;
+            let (dx, dy) = AROUND[heading];
+            let next = Vect2D::new(at.x + dx, at.y + dy);
 
-            if err < 0 {
-                if c1.y < c2.y {
-                    c1.y += 1;
-                } else {
-                    c1.y -= 1;
+            if self.is_area(next, color) {
+                return (next, heading);
+            }
+        }
+
+        (at, heading)
+    }
+
+    /// One neighbour of `at` in the area for each group of them connected through the
+    /// pixels around `at`, the number of groups and the group `from` is in, if any.
+    fn neighbour_groups(
+        &self,
+        at: Vect2D,
+        from: Option<Vect2D>,
+        color: bool,
+    ) -> ([Vect2D; 4], usize, Option<usize>) {
+        let around = AROUND.map(|(dx, dy)| Vect2D::new(at.x + dx, at.y + dy));
+        let inside = around.map(|c| self.is_area(c, color));
+        let mut groups = [at; 4];
+
+        let gap = match inside.iter().position(|inside| !inside) {
+            Some(gap) => gap,
+            None => {
+                groups[0] = around[0];
+                return (groups, 1, from.map(|_| 0));
+            }
+        };
+
+        let mut len = 0;
+        let mut from_group = None;
+        let mut counted = false;
+
+        // going round from a pixel outside the area, so that no group wraps around
+        for i in (gap + 1..gap + 9).map(|i| i % 8) {
+            if !inside[i] {
+                counted = false;
+                continue;
+            }
+
+            if i % 2 == 0 && !counted {
+                groups[len] = around[i];
+                len += 1;
+                counted = true;
+            }
+
+            if Some(around[i]) == from {
+                from_group = Some(len - 1);
+            }
+        }
+
+        (groups, len, from_group)
+    }
+
+    /// Whether `a` and `b`, neighbours of `at` in the area, are still connected once `at`
+    /// is filled, or `None` when that takes more than `steps` steps, which are counted down.
+    /// Walking along the edge of the area with `at` on the right goes round the whole part
+    /// of the area on that side, so from either of them it meets the other exactly when they
+    /// are connected. Both walks go at once and the shorter one decides.
+    fn connected_without(
+        &self,
+        at: Vect2D,
+        a: Vect2D,
+        b: Vect2D,
+        color: bool,
+        steps: &mut u32,
+    ) -> Option<bool> {
+        let inside = |c: Vect2D| c != at && self.is_area(c, color);
+        let mut from_a = EdgeWalk::new(a, at);
+        let mut from_b = EdgeWalk::new(b, at);
+
+        while *steps > 0 {
+            *steps -= 1;
+
+            for (walk, other) in [(&mut from_a, b), (&mut from_b, a)] {
+                if walk.at() == other {
+                    return Some(true);
+                }
+
+                if !walk.step(inside) {
+                    return Some(false);
                 }
-                err += dx as i32;
             }
         }
 
+        None
+    }
+
+    fn bounds(&self) -> Rect {
+        Rect::with_size(Vect2D::new(0, 0), self.width as u32, self.height as u32)
+    }
+
+    pub fn clear_display(&mut self) -> crate::Result<()> {
+        self.mark_all_dirty();
+        self.display.clear_display()
+    }
+
+    /// Sends the rows of `buffer` modified since the last call to the display.
+    pub fn draw(&mut self) -> crate::Result<()> {
+        let dirty_count = self
+            .dirty_lines
+            .as_ref()
+            .iter()
+            .filter(|dirty| **dirty)
+            .count();
+
+        if dirty_count == 0 {
+            return Ok(());
+        }
+
+        if dirty_count * FULL_REFRESH_RATIO.1
+            > self.dirty_lines.as_ref().len() * FULL_REFRESH_RATIO.0
+        {
+            self.display.borrow_mut().refresh(self.buffer.view())?;
+        } else {
+            self.display
+                .refresh_dirty(self.buffer.view(), self.dirty_lines.as_ref())?;
+        }
+
+        self.dirty_lines.as_mut().fill(false);
+
         Ok(())
     }
 
-    fn draw_hline(&mut self, c: Vect2D, len: u16, color: bool) -> anyhow::Result<()> {
-        let left_overlap = c.x % 8;
-        let right_overflow = (c.x + len) % 8;
+    /// Waits until the last `draw` has reached the panel, for displays that transfer in the
+    /// background.
+    pub fn flush(&mut self) -> crate::Result<()> {
+        self.display.flush()
+    }
 
-        if (8 - left_overlap) > len && left_overlap != 0 {
-            if color {
-                self.buffer[c.y as usize][((c.x - left_overlap) / 8) as usize] |=
-                    CAP[(7 - left_overlap) as usize] & SHO[(7 - left_overlap - len) as usize];
-            } else {
-                self.buffer[c.y as usize][((c.x - left_overlap) / 8) as usize] &=
-                    SHO[(7 - left_overlap) as usize] | CAP[(7 - left_overlap - len) as usize];
+    /// Marks a row as changed, for when `buffer` is modified directly.
+    pub fn mark_dirty(&mut self, line: u16) {
+        if let Some(dirty) = self.dirty_lines.as_mut().get_mut(line as usize) {
+            *dirty = true;
+        }
+    }
+
+    /// Makes the next `draw` send every row, e.g. after the panel lost its contents.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_lines.as_mut().fill(true);
+    }
+
+    /// Reads a single pixel, which must be on the canvas.
+    fn pixel(&self, x: usize, y: usize) -> bool {
+        self.buffer[y][x / 8] & SET[x % 8] != 0
+    }
+
+    /// Draws a single pixel, which must be on the canvas, as part of the foreground of what
+    /// is drawn or of its background.
+    fn put_pixel(&mut self, x: usize, y: usize, color: bool, foreground: bool) {
+        let value = if color { 0xFF } else { 0x00 };
+        let foreground = if foreground { 0xFF } else { 0x00 };
+        self.write(y, x / 8, SET[x % 8], value, foreground);
+    }
+
+    /// Draws a single pixel like `set_pixel`, but as background for `DrawMode::Transparent`.
+    fn set_background_pixel(&mut self, c: Vect2D, color: bool) {
+        if self.clip.contains(c) {
+            self.put_pixel(c.x as usize, c.y as usize, color, false);
+            self.mark_dirty(c.y as u16);
+        }
+    }
+
+    /// Combines the bits of `value` selected by `mask` into byte `index` of row `y`
+    /// according to the draw mode, `foreground` telling which of them belong to the shape.
+    fn write(&mut self, y: usize, index: usize, mask: u8, value: u8, foreground: u8) {
+        let mode = self.draw_mode;
+        let byte = &mut self.buffer[y][index];
+        *byte = mode.apply(*byte, mask, value, foreground);
+    }
+
+    /// Draws the Bresenham line between `c1` and `c2`, see [`LineSteps`], leaving out the
+    /// end with the lower major coordinate. Every pixel takes its minor coordinate from the
+    /// step before.
+    ///
+    /// The steps outside the clip are skipped by working out where the line enters and
+    /// leaves it, so lines reaching far off the canvas cost no more than their visible
+    /// part.
+    fn clipped_line(&mut self, c1: Vect2D, c2: Vect2D, color: bool) {
+        let line = LineSteps::new(c1, c2);
+        let (major_clip, minor_clip) = if line.steep {
+            (
+                (self.clip.min.y, self.clip.max.y),
+                (self.clip.min.x, self.clip.max.x),
+            )
+        } else {
+            (
+                (self.clip.min.x, self.clip.max.x),
+                (self.clip.min.y, self.clip.max.y),
+            )
+        };
+        let (low, high) = line.moves_within(minor_clip.0, minor_clip.1);
+        let moved = line.moved(low, high);
+
+        // step `k` is drawn where step `k - 1` moved to
+        let first = (moved.0 + 1).max(major_clip.0 as i64 - line.start.0).max(1);
+        let last = (moved.1 + 1)
+            .min(major_clip.1 as i64 - 1 - line.start.0)
+            .min(line.steps);
+
+        if first > last {
+            return;
+        }
+
+        let (mut moves, mut error) = line.moves(first - 1);
+
+        for k in first..=last {
+            let (x, y) = line.pixel(line.start.0 + k, line.start.1 + line.sign * moves);
+
+            self.put_pixel(x as usize, y as usize, color, true);
+            self.mark_dirty(y as u16);
+
+            error -= line.rise;
+
+            if error < 0 {
+                moves += 1;
+                error += line.steps;
             }
+        }
+    }
 
-            return Ok(());
+    /// Fills a polygon whose vertices are given in `1 / scale` pixels, see [`PolygonSpans`].
+    fn fill_scaled_polygon(&mut self, vertices: &[Vect2D], scale: i32, rule: FillRule, ink: Ink) {
+        let (top, bottom) = vertices
+            .iter()
+            .fold((i32::MAX, i32::MIN), |(top, bottom), vertex| {
+                (top.min(vertex.y), bottom.max(vertex.y))
+            });
+        let (top, bottom) = (
+            top.div_euclid(scale),
+            bottom.div_euclid(scale).saturating_add(1),
+        );
+
+        for y in top.max(self.clip.min.y)..bottom.min(self.clip.max.y) {
+            for (x0, x1) in PolygonSpans::scaled(vertices, scale, rule, y) {
+                let (x0, x1) = (x0.max(self.clip.min.x), x1.min(self.clip.max.x));
+
+                if x0 < x1 {
+                    self.span(Vect2D::new(x0, y), (x1 - x0) as u32, ink);
+                }
+            }
         }
+    }
 
-        if left_overlap != 0 {
-            if color {
-                self.buffer[c.y as usize][((c.x - left_overlap) / 8) as usize] |=
-                    CAP[(7 - left_overlap) as usize];
-            } else {
-                self.buffer[c.y as usize][((c.x - left_overlap) / 8) as usize] &=
-                    SHO[(7 - left_overlap) as usize];
+    /// Draws the part of an elliptic ring within `sector`, see [`RingRows`], row span by
+    /// row span.
+    fn draw_ring(
+        &mut self,
+        center: Vect2D,
+        radius_x: u32,
+        radius_y: u32,
+        inner: Option<(u32, u32)>,
+        sector: Sector,
+        ink: Ink,
+    ) {
+        let (a, b) = (
+            radius_x.min(MAX_RADIUS) as i32,
+            radius_y.min(MAX_RADIUS) as i32,
+        );
+        let bounds = Rect::new(
+            Vect2D::new(center.x.saturating_sub(a), center.y.saturating_sub(b)),
+            Vect2D::new(
+                center.x.saturating_add(a + 1),
+                center.y.saturating_add(b + 1),
+            ),
+        );
+
+        if matches!(sector, Sector::Empty) || bounds.intersect(&self.clip).is_empty() {
+            return;
+        }
+
+        for (dy, x0, x1) in RingRows::new(radius_x, radius_y, inner) {
+            for dy in [dy, -dy].into_iter().take(if dy == 0 { 1 } else { 2 }) {
+                let y = center.y.saturating_add(dy);
+
+                if y < self.clip.min.y || y >= self.clip.max.y {
+                    continue;
+                }
+
+                if x0 == 0 {
+                    self.draw_sector_span(center, dy, -x1, x1, sector, ink);
+                } else {
+                    self.draw_sector_span(center, dy, -x1, -x0, sector, ink);
+                    self.draw_sector_span(center, dy, x0, x1, sector, ink);
+                }
             }
         }
+    }
+
+    /// Draws the pixels `x0..=x1` of row `dy` around `center` that lie within `sector`, in
+    /// as few spans as possible.
+    fn draw_sector_span(
+        &mut self,
+        center: Vect2D,
+        dy: i32,
+        x0: i32,
+        x1: i32,
+        sector: Sector,
+        ink: Ink,
+    ) {
+        let y = center.y.saturating_add(dy);
+
+        if let Sector::Full = sector {
+            return self.span(
+                Vect2D::new(center.x.saturating_add(x0), y),
+                (x1 - x0 + 1) as u32,
+                ink,
+            );
+        }
 
-        if right_overflow != 0 {
-            if color {
-                self.buffer[c.y as usize][((c.x + len - right_overflow) / 8) as usize] |=
-                    SHO[(7 - right_overflow) as usize];
-            } else {
-                self.buffer[c.y as usize][((c.x + len - right_overflow) / 8) as usize] &=
-                    CAP[(7 - right_overflow) as usize];
+        let mut run = None;
+
+        for dx in x0..=x1 {
+            match (sector.contains(dx, dy), run) {
+                (true, None) => run = Some(dx),
+                (false, Some(start)) => {
+                    self.span(
+                        Vect2D::new(center.x.saturating_add(start), y),
+                        (dx - start) as u32,
+                        ink,
+                    );
+                    run = None;
+                }
+                _ => {}
             }
         }
 
-        for i in (c.x + 7) / 8..(c.x + (len - right_overflow)) / 8 {
-            self.buffer[c.y as usize][i as usize] = color as u8 * 0xff;
+        if let Some(start) = run {
+            self.span(
+                Vect2D::new(center.x.saturating_add(start), y),
+                (x1 - start + 1) as u32,
+                ink,
+            );
         }
+    }
 
-        Ok(())
+    /// Paints the part of the row span starting at `c` inside the clip rectangle with the
+    /// row of `ink` it falls on.
+    fn span(&mut self, c: Vect2D, len: u32, ink: Ink) {
+        let span = Rect::with_size(c, len, 1).intersect(&self.clip);
+
+        if span.is_empty() {
+            return;
+        }
+
+        let y = span.min.y as u16;
+
+        let row = y as usize % 8;
+        let (value, foreground) = (ink.value[row], ink.foreground[row]);
+
+        self.fill_span(y, span.min.x as u16, span.max.x as u16, value, foreground);
+        self.mark_dirty(y);
     }
 
-    fn draw_vline(&mut self, c: Vect2D, height: u16, color: bool) -> anyhow::Result<()> {
-        if c.x >= self.width || c.y >= self.height || c.y + height >= self.width {
-            return Err(anyhow!("Line dimensions out of bounds"));
+    /// Draws pixels `x0..x1` of row `y` with the matching bits of `value` and `foreground`,
+    /// a whole byte at a time. The span must be on the canvas.
+    fn fill_span(&mut self, y: u16, x0: u16, x1: u16, value: u8, foreground: u8) {
+        let y = y as usize;
+        let (first, last) = (x0 as usize / 8, (x1 as usize - 1) / 8);
+        let head = 0xFFu8 << (x0 % 8);
+        let tail = 0xFFu8 >> (7 - (x1 - 1) % 8);
+
+        if first == last {
+            self.write(y, first, head & tail, value, foreground);
+            return;
         }
 
-        let offset = c.x % 8;
-        let coord = (c.x - offset) / 8;
+        self.write(y, first, head, value, foreground);
 
-        for i in 0..height {
-            if color {
-                self.buffer[(c.y + i) as usize][coord as usize] |= SET[offset as usize];
-            } else {
-                self.buffer[(c.y + i) as usize][coord as usize] &= CLR[offset as usize];
+        if self.draw_mode == DrawMode::Replace {
+            self.buffer[y][first + 1..last].fill(value);
+        } else {
+            for index in first + 1..last {
+                self.write(y, index, 0xFF, value, foreground);
             }
         }
 
+        self.write(y, last, tail, value, foreground);
+    }
+}
+
+/// A row span `flood_fill` still has to look at, `x0..=x1` of row `y`, found from the row
+/// in direction `-dy`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FloodFillSpan {
+    y: u16,
+    x0: u16,
+    x1: u16,
+    dy: i8,
+}
+
+/// The spans `flood_fill` still has to look at, in storage of a fixed capacity, taken in
+/// the order they were found so that the fill spreads out evenly from the seed.
+struct SpanQueue<'s> {
+    spans: &'s mut [FloodFillSpan],
+    first: usize,
+    len: usize,
+    clip: Rect,
+}
+
+impl<'s> SpanQueue<'s> {
+    fn new(spans: &'s mut [FloodFillSpan], clip: Rect) -> Self {
+        SpanQueue {
+            spans,
+            first: 0,
+            len: 0,
+            clip,
+        }
+    }
+
+    /// Remembers to look at the row next to `x0..=x1` of row `y` in direction `dy`, unless
+    /// that is outside the clip rectangle. Returns `false` when the queue is full.
+    fn push(&mut self, y: i32, x0: i32, x1: i32, dy: i8) -> bool {
+        let next = y + dy as i32;
+
+        if next < self.clip.min.y || next >= self.clip.max.y {
+            return true;
+        }
+
+        if self.len == self.spans.len() {
+            return false;
+        }
+
+        self.spans[(self.first + self.len) % self.spans.len()] = FloodFillSpan {
+            y: next as u16,
+            x0: x0 as u16,
+            x1: x1 as u16,
+            dy,
+        };
+        self.len += 1;
+
+        true
+    }
+
+    fn pop(&mut self) -> Option<(i32, i32, i32, i8)> {
+        self.len = self.len.checked_sub(1)?;
+        let span = self.spans[self.first];
+        self.first = (self.first + 1) % self.spans.len();
+
+        Some((span.y as i32, span.x0 as i32, span.x1 as i32, span.dy))
+    }
+}
+
+/// Tells when the walk of `fill_piece` comes back to a position and heading it had before
+/// without filling a pixel, remembering a single one of them: the one it had after 1, 2, 4,
+/// 8... steps, which a walk going round in circles runs into within twice its lap.
+struct Lap {
+    mark: (Vect2D, usize),
+    steps: u64,
+    length: u64,
+    closed: bool,
+}
+
+impl Lap {
+    fn new(at: Vect2D, heading: usize) -> Self {
+        Lap {
+            mark: (at, heading),
+            steps: 0,
+            length: 1,
+            closed: false,
+        }
+    }
+
+    fn step(&mut self, at: Vect2D, heading: usize) {
+        if (at, heading) == self.mark {
+            self.closed = true;
+            return;
+        }
+
+        self.steps += 1;
+
+        if self.steps == self.length {
+            self.mark = (at, heading);
+            self.steps = 0;
+            self.length *= 2;
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+/// A walk along the edge of a `flood_fill` area keeping the outside on its right, one
+/// pixel or turn at a time.
+struct EdgeWalk {
+    at: Vect2D,
+    dx: i32,
+    dy: i32,
+    start: (Vect2D, i32, i32),
+}
+
+impl EdgeWalk {
+    /// Starts at `at` with its `wall` neighbour on the right.
+    fn new(at: Vect2D, wall: Vect2D) -> Self {
+        let (dx, dy) = (wall.y - at.y, at.x - wall.x);
+
+        EdgeWalk {
+            at,
+            dx,
+            dy,
+            start: (at, dx, dy),
+        }
+    }
+
+    fn at(&self) -> Vect2D {
+        self.at
+    }
+
+    /// Takes the next step, returning `false` once back where the walk started.
+    fn step(&mut self, inside: impl Fn(Vect2D) -> bool) -> bool {
+        let (dx, dy) = (self.dx, self.dy);
+        let ahead = Vect2D::new(self.at.x + dx, self.at.y + dy);
+        let round = Vect2D::new(ahead.x - dy, ahead.y + dx);
+
+        if !inside(ahead) {
+            // a wall ahead, turn left to keep it on the right
+            (self.dx, self.dy) = (dy, -dx);
+        } else if inside(round) {
+            // the wall on the right ends, turn round its corner
+            self.at = round;
+            (self.dx, self.dy) = (-dy, dx);
+        } else {
+            self.at = ahead;
+        }
+
+        (self.at, self.dx, self.dy) != self.start
+    }
+}
+
+/// How far apart `a` and `b` are, saturating at what a length can hold.
+fn distance(a: i32, b: i64) -> u32 {
+    (a as i64 - b).unsigned_abs().min(u32::MAX as u64) as u32
+}
+
+impl<B, L> SetPixel<bool> for MonoGraphics<'_, B, L>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+    L: AsRef<[bool]> + AsMut<[bool]>,
+{
+    fn set_pixel(&mut self, c: Vect2D, color: bool) -> crate::Result<()> {
+        if !self.clip.contains(c) {
+            return Ok(());
+        }
+
+        self.put_pixel(c.x as usize, c.y as usize, color, true);
+        self.mark_dirty(c.y as u16);
+
+        Ok(())
+    }
+}
+
+impl<B, L> Draw<bool> for MonoGraphics<'_, B, L>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+    L: AsRef<[bool]> + AsMut<[bool]>,
+{
+    fn clear(&mut self, color: bool) -> crate::Result<()> {
+        if self.clip != self.bounds() || self.draw_mode != DrawMode::Replace {
+            let clip = self.clip;
+
+            for y in clip.min.y..clip.max.y {
+                self.draw_hline(Vect2D::new(clip.min.x, y), clip.width(), color)?;
+            }
+
+            return Ok(());
+        }
+
+        let line_color = if color { 0xFF } else { 0x00 };
+
+        self.buffer.fill(line_color);
+
+        self.mark_all_dirty();
+
+        Ok(())
+    }
+
+    fn draw_line(&mut self, c1: Vect2D, c2: Vect2D, color: bool) -> crate::Result<()> {
+        self.clipped_line(c1, c2, color);
+
+        Ok(())
+    }
+
+    fn draw_hline(&mut self, c: Vect2D, len: u32, color: bool) -> crate::Result<()> {
+        self.span(c, len, Pattern::SOLID.ink(color));
+
+        Ok(())
+    }
+
+    fn draw_vline(&mut self, c: Vect2D, height: u32, color: bool) -> crate::Result<()> {
+        let span = Rect::with_size(c, 1, height).intersect(&self.clip);
+
+        if span.is_empty() {
+            return Ok(());
+        }
+
+        for y in span.min.y..span.max.y {
+            self.put_pixel(span.min.x as usize, y as usize, color, true);
+            self.mark_dirty(y as u16);
+        }
+
         Ok(())
     }
 
@@ -189,37 +1092,26 @@ impl Draw<bool> for MonoGraphics<'_> {
         corner1: Vect2D,
         corner2: Vect2D,
         color: bool,
-    ) -> anyhow::Result<()> {
-        unsafe {
-            self.draw_hline(
-                corner1,
-                abs(corner1.x as i32 - corner2.x as i32 - 1).try_into()?,
-                color,
-            )?;
-
-            self.draw_hline(
-                Vect2D {
-                    x: corner1.x,
-                    y: corner2.y,
-                },
-                abs(corner1.x as i32 - corner2.x as i32 - 1).try_into()?,
-                color,
-            )?;
+    ) -> crate::Result<()> {
+        let (x0, x1) = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let (y0, y1) = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+        let width = distance(x1, x0 as i64 - 1);
+        let height = distance(y1, y0 as i64 - 1);
 
-            self.draw_vline(
-                corner1,
-                abs(corner1.y as i32 - corner2.y as i32 - 1).try_into()?,
-                color,
-            )?;
+        // the corners belong to the horizontal edges and no pixel is drawn twice, which
+        // would undo itself in `DrawMode::Xor`
+        self.draw_hline(Vect2D::new(x0, y0), width, color)?;
 
-            self.draw_vline(
-                Vect2D {
-                    x: corner2.x,
-                    y: corner1.y,
-                },
-                abs(corner1.y as i32 - corner2.y as i32 - 1).try_into()?,
-                color,
-            )?;
+        if y1 != y0 {
+            self.draw_hline(Vect2D::new(x0, y1), width, color)?;
+        }
+
+        let top = y0.saturating_add(1);
+
+        self.draw_vline(Vect2D::new(x0, top), height.saturating_sub(2), color)?;
+
+        if x1 != x0 {
+            self.draw_vline(Vect2D::new(x1, top), height.saturating_sub(2), color)?;
         }
 
         Ok(())
@@ -230,94 +1122,402 @@ impl Draw<bool> for MonoGraphics<'_> {
         corner1: Vect2D,
         corner2: Vect2D,
         color: bool,
-    ) -> anyhow::Result<()> {
-        unsafe {
-            for i in 0..abs(corner1.y as i32 - corner2.y as i32).try_into()? {
-                self.draw_hline(
-                    Vect2D {
-                        x: corner1.x,
-                        y: corner1.y + i,
-                    },
-                    abs(corner1.x as i32 - corner2.x as i32).try_into()?,
-                    color,
-                )?;
+    ) -> crate::Result<()> {
+        let (x0, x1) = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let (y0, y1) = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+        let ink = self.fill_pattern.ink(color);
+        let area = Rect::with_size(
+            Vect2D::new(x0, y0),
+            distance(x1, x0 as i64),
+            distance(y1, y0 as i64),
+        )
+        .intersect(&self.clip);
+
+        for y in area.min.y..area.max.y {
+            self.span(Vect2D::new(area.min.x, y), area.width(), ink);
+        }
+
+        Ok(())
+    }
+
+    fn draw_thick_line(
+        &mut self,
+        c1: Vect2D,
+        c2: Vect2D,
+        width: u32,
+        cap: LineCap,
+        color: bool,
+    ) -> crate::Result<()> {
+        if width == 0 {
+            return Ok(());
+        }
+
+        let mut outline = [Vect2D::default(); MAX_STROKE_VERTICES];
+        let len = stroke_outline(c1, c2, width, cap, self.clip, &mut outline);
+
+        if len == 0 {
+            return Ok(());
+        }
+
+        self.fill_scaled_polygon(
+            &outline[..len],
+            SUBPIXEL,
+            FillRule::NonZero,
+            Pattern::SOLID.ink(color),
+        );
+
+        Ok(())
+    }
+
+    fn draw_round_rect(
+        &mut self,
+        corner1: Vect2D,
+        corner2: Vect2D,
+        radius: u32,
+        color: bool,
+    ) -> crate::Result<()> {
+        let (x0, x1) = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let (y0, y1) = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+        let radius = radius
+            .min(distance(x1, x0 as i64).min(distance(y1, y0 as i64)) / 2)
+            .min(MAX_RADIUS);
+        let r = radius as i32;
+        let (left, right, top, bottom) = (x0 + r, x1 - r, y0 + r, y1 - r);
+
+        for (dy, a, b) in RingRows::new(radius, radius, Some((radius, radius))) {
+            let rows = if dy == 0 && top == bottom { 1 } else { 2 };
+
+            for y in [top - dy, bottom + dy].into_iter().take(rows) {
+                if a == 0 {
+                    self.draw_hline(
+                        Vect2D::new(left - b, y),
+                        distance(right + b, (left - b) as i64 - 1),
+                        color,
+                    )?;
+                } else {
+                    self.draw_hline(Vect2D::new(left - b, y), (b - a + 1) as u32, color)?;
+                    self.draw_hline(Vect2D::new(right + a, y), (b - a + 1) as u32, color)?;
+                }
+            }
+        }
+
+        if bottom as i64 - top as i64 > 1 {
+            let height = distance(bottom, top as i64 + 1);
+
+            self.draw_vline(Vect2D::new(x0, top + 1), height, color)?;
+
+            if x1 != x0 {
+                self.draw_vline(Vect2D::new(x1, top + 1), height, color)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_round_rect(
+        &mut self,
+        corner1: Vect2D,
+        corner2: Vect2D,
+        radius: u32,
+        color: bool,
+    ) -> crate::Result<()> {
+        let (x0, x1) = (corner1.x.min(corner2.x), corner1.x.max(corner2.x));
+        let (y0, y1) = (corner1.y.min(corner2.y), corner1.y.max(corner2.y));
+
+        if x0 == x1 || y0 == y1 {
+            return Ok(());
+        }
+
+        let radius = radius
+            .min((distance(x1, x0 as i64).min(distance(y1, y0 as i64)) - 1) / 2)
+            .min(MAX_RADIUS);
+        let r = radius as i32;
+        let (left, right, top, bottom) = (x0 + r, x1 - 1 - r, y0 + r, y1 - 1 - r);
+
+        let ink = self.fill_pattern.ink(color);
+
+        for (dy, _, half_width) in RingRows::new(radius, radius, None) {
+            let rows = if dy == 0 && top == bottom { 1 } else { 2 };
+
+            for y in [top - dy, bottom + dy].into_iter().take(rows) {
+                self.span(
+                    Vect2D::new(left - half_width, y),
+                    distance(right + half_width, (left - half_width) as i64 - 1),
+                    ink,
+                );
             }
         }
 
+        for y in (top + 1).max(self.clip.min.y)..bottom.min(self.clip.max.y) {
+            self.span(Vect2D::new(x0, y), distance(x1, x0 as i64), ink);
+        }
+
+        Ok(())
+    }
+
+    fn draw_circle(&mut self, center: Vect2D, radius: u32, color: bool) -> crate::Result<()> {
+        self.draw_ellipse(center, radius, radius, color)
+    }
+
+    fn fill_circle(&mut self, center: Vect2D, radius: u32, color: bool) -> crate::Result<()> {
+        self.fill_ellipse(center, radius, radius, color)
+    }
+
+    fn draw_ellipse(
+        &mut self,
+        center: Vect2D,
+        radius_x: u32,
+        radius_y: u32,
+        color: bool,
+    ) -> crate::Result<()> {
+        self.draw_ring(
+            center,
+            radius_x,
+            radius_y,
+            Some((radius_x, radius_y)),
+            Sector::Full,
+            Pattern::SOLID.ink(color),
+        );
+
         Ok(())
     }
 
-    fn draw_texture(&mut self, corner: Vect2D, texture: &Vec<Vec<u8>>) -> anyhow::Result<()> {
-        if texture.len() == 0 {
+    fn fill_ellipse(
+        &mut self,
+        center: Vect2D,
+        radius_x: u32,
+        radius_y: u32,
+        color: bool,
+    ) -> crate::Result<()> {
+        let ink = self.fill_pattern.ink(color);
+        self.draw_ring(center, radius_x, radius_y, None, Sector::Full, ink);
+
+        Ok(())
+    }
+
+    fn draw_arc(
+        &mut self,
+        center: Vect2D,
+        radius: u32,
+        start_angle: i32,
+        end_angle: i32,
+        thickness: u32,
+        color: bool,
+    ) -> crate::Result<()> {
+        if thickness == 0 {
             return Ok(());
         }
 
-        for i in corner.y..texture.len().clamp(0, self.height as usize) as u16 {
-            for j in corner.x..texture[0].len().clamp(0, self.width as usize) as u16 {
-                self.buffer[i as usize][j as usize] = texture[i as usize][j as usize];
+        let inner = (thickness <= radius).then(|| radius - thickness + 1);
+
+        self.draw_ring(
+            center,
+            radius,
+            radius,
+            inner.map(|inner| (inner, inner)),
+            Sector::new(start_angle, end_angle),
+            Pattern::SOLID.ink(color),
+        );
+
+        Ok(())
+    }
+
+    fn draw_triangle(
+        &mut self,
+        c1: Vect2D,
+        c2: Vect2D,
+        c3: Vect2D,
+        color: bool,
+    ) -> crate::Result<()> {
+        self.draw_polygon(&[c1, c2, c3], color)
+    }
+
+    fn fill_triangle(
+        &mut self,
+        c1: Vect2D,
+        c2: Vect2D,
+        c3: Vect2D,
+        color: bool,
+    ) -> crate::Result<()> {
+        self.fill_polygon(&[c1, c2, c3], FillRule::NonZero, color)
+    }
+
+    fn draw_polygon(&mut self, vertices: &[Vect2D], color: bool) -> crate::Result<()> {
+        // every edge is drawn without its last pixel, which the next one starts on, and
+        // without the pixels of earlier edges it crosses or runs along, for no pixel to be
+        // drawn twice. An outline of a single point is just that pixel
+        let point = vertices.iter().all(|vertex| *vertex == vertices[0]);
+        let edges = || vertices.iter().zip(vertices.iter().cycle().skip(1));
+        let bounds = |(from, to): (&Vect2D, &Vect2D)| {
+            (
+                (from.x.min(to.x) as i64, from.y.min(to.y) as i64),
+                (from.x.max(to.x) as i64, from.y.max(to.y) as i64),
+            )
+        };
+        let on_edge = |(from, to): (&Vect2D, &Vect2D), x: i64, y: i64| {
+            let (min, max) = bounds((from, to));
+
+            (min.0..=max.0).contains(&x)
+                && (min.1..=max.1).contains(&y)
+                && (point || (x, y) != (to.x as i64, to.y as i64))
+                && LineSteps::new(*from, *to).contains(x, y)
+        };
+
+        for (i, (from, to)) in edges().enumerate() {
+            let line = LineSteps::new(*from, *to);
+            let (first, last) = line.steps_within(self.clip);
+
+            if first > last {
+                continue;
+            }
+
+            // the earlier edges whose bounds meet those of this one, which are the only
+            // ones it can share pixels with
+            let (min, max) = bounds((from, to));
+            let (near, far) = edges()
+                .take(i)
+                .enumerate()
+                .filter(|(_, other)| {
+                    let (other_min, other_max) = bounds(*other);
+
+                    other_min.0 <= max.0
+                        && min.0 <= other_max.0
+                        && other_min.1 <= max.1
+                        && min.1 <= other_max.1
+                })
+                .fold((i, 0), |(near, far), (j, _)| (near.min(j), far.max(j + 1)));
+            let (mut moves, mut error) = line.moves(first);
+
+            for k in first..=last {
+                let (x, y) = line.pixel(line.start.0 + k, line.start.1 + line.sign * moves);
+
+                if (point || (x, y) != (to.x as i64, to.y as i64))
+                    && !edges()
+                        .take(far)
+                        .skip(near)
+                        .any(|other| on_edge(other, x, y))
+                {
+                    self.put_pixel(x as usize, y as usize, color, true);
+                    self.mark_dirty(y as u16);
+                }
+
+                error -= line.rise;
+
+                if error < 0 {
+                    moves += 1;
+                    error += line.steps;
+                }
             }
         }
 
         Ok(())
     }
 
-    fn draw_texture_from_flash(&mut self, corner: Vect2D, path: &str) -> anyhow::Result<()> {
-        if corner.x >= self.width || corner.y >= self.height {
-            return Err(anyhow!("Corner coordinates are out of screen bounds"));
+    fn fill_polygon(
+        &mut self,
+        vertices: &[Vect2D],
+        rule: FillRule,
+        color: bool,
+    ) -> crate::Result<()> {
+        let ink = self.fill_pattern.ink(color);
+        self.fill_scaled_polygon(vertices, 1, rule, ink);
+
+        Ok(())
+    }
+
+    fn draw_texture(&mut self, corner: Vect2D, texture: FrameBufferView<'_>) -> crate::Result<()> {
+        let visible = Rect::with_size(corner, texture.width() as u32, texture.height() as u32)
+            .intersect(&self.clip);
+
+        if visible.is_empty() {
+            return Ok(());
         }
 
-        let texture = std::fs::read(path)?;
+        // with the texture aligned to the bytes of the buffer, the bytes it fully covers
+        // are drawn as they are and only the pixels at the edges are drawn one by one. The
+        // black pixels are the foreground, for `DrawMode::Transparent`
+        let (first_byte, last_byte) = if corner.x.rem_euclid(8) == 0 {
+            ((visible.min.x + 7) / 8, visible.max.x / 8)
+        } else {
+            (0, 0)
+        };
+
+        for y in visible.min.y..visible.max.y {
+            let source = texture.row((y - corner.y) as u16);
+
+            if first_byte < last_byte {
+                let offset = (first_byte - corner.x / 8) as usize;
+
+                for (index, value) in (first_byte..last_byte).zip(&source[offset..]) {
+                    self.write(y as usize, index as usize, 0xFF, *value, !*value);
+                }
+            }
 
-        let w = texture[0] as u16 | ((texture[1] as u16) << 8);
-        let h = texture[2] as u16 | ((texture[3] as u16) << 8);
-        let acutal_x = corner.x + 8 - (corner.x % 8);
-        let total_h = corner.y + h;
-        let total_w = acutal_x + w;
+            for x in visible.min.x..visible.max.x {
+                if first_byte < last_byte && x >= first_byte * 8 && x < last_byte * 8 {
+                    continue;
+                }
 
-        for i in corner.y..total_h - (total_h % self.height) {
-            for j in acutal_x / 8..(total_w - (total_w % self.width)) / 8 {
-                self.buffer[i as usize][j as usize] =
-                    texture[(i * self.width / 8 + j + 4) as usize];
+                let texture_x = (x - corner.x) as usize;
+                let color = source[texture_x / 8] & SET[texture_x % 8] != 0;
+                self.put_pixel(x as usize, y as usize, color, !color);
             }
+
+            self.mark_dirty(y as u16);
         }
 
         Ok(())
     }
+
+    #[cfg(feature = "std")]
+    fn draw_texture_from_flash(&mut self, corner: Vect2D, path: &str) -> crate::Result<()> {
+        let texture = crate::filesystem::read_texture_to_buffer(path)?;
+        self.draw_texture(corner, texture.view())
+    }
 }
 
-impl Print<bool> for MonoGraphics<'_> {
-    fn put_char(&mut self, c: &Vect2D, chr: char, color: bool) -> anyhow::Result<()> {
-        if c.x >= self.width || c.y >= self.height {
+impl<B, L> Print<bool> for MonoGraphics<'_, B, L>
+where
+    B: AsRef<[u8]> + AsMut<[u8]>,
+    L: AsRef<[bool]> + AsMut<[bool]>,
+{
+    fn put_char(&mut self, c: &Vect2D, chr: char, color: bool) -> crate::Result<()> {
+        if Rect::with_size(*c, 6, 8).intersect(&self.clip).is_empty() {
             return Ok(());
         }
 
+        // the font only covers the first 256 code points, anything past them is drawn as `?`
+        let glyph = u8::try_from(chr).unwrap_or(b'?') as usize;
+
+        // the glyph is the foreground, the rest of the cell its background
         for i in 0..5 {
-            let mut line: u8 = GLCD_FONT[(chr as usize) * 5 + i];
+            let mut line: u8 = GLCD_FONT[glyph * 5 + i];
 
             for j in 0..8 {
+                let pixel = Vect2D {
+                    x: c.x + i as i32,
+                    y: c.y + j,
+                };
+
                 if (line & 1) == 1 {
-                    self.set_pixel(
-                        Vect2D {
-                            x: c.x + i as u16,
-                            y: c.y + j,
-                        },
-                        color,
-                    )?;
+                    self.set_pixel(pixel, color)?;
                 } else {
-                    self.set_pixel(
-                        Vect2D {
-                            x: c.x + i as u16,
-                            y: c.y + j,
-                        },
-                        !color,
-                    )?;
+                    self.set_background_pixel(pixel, !color);
                 }
 
                 line >>= 1;
             }
         }
 
-        self.draw_vline(Vect2D { x: c.x + 5, y: c.y }, 8, !color)?;
+        for j in 0..8 {
+            self.set_background_pixel(
+                Vect2D {
+                    x: c.x + 5,
+                    y: c.y + j,
+                },
+                !color,
+            );
+        }
 
         Ok(())
     }
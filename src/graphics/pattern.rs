@@ -0,0 +1,60 @@
+use super::Dither;
+
+/// An 8x8 pattern filled shapes are painted with, to show shades of grey on a panel that
+/// only has black and white.
+///
+/// Each byte is a row and bit `n` the pixel in column `n`, like in the frame buffer. Pixels
+/// whose bit is set get the colour the shape is filled with and the others the opposite
+/// colour. The pattern is anchored to the canvas rather than to the shape, so neighbouring
+/// shapes with the same pattern join seamlessly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pattern(pub [u8; 8]);
+
+impl Pattern {
+    /// Every pixel in the fill colour.
+    pub const SOLID: Pattern = Pattern([0xFF; 8]);
+    /// Squares of 4x4 pixels.
+    pub const CHECKER: Pattern = Pattern([0x0F, 0x0F, 0x0F, 0x0F, 0xF0, 0xF0, 0xF0, 0xF0]);
+    /// Diagonal lines 4 pixels apart, falling to the right.
+    pub const HATCH: Pattern = Pattern([0x11, 0x22, 0x44, 0x88, 0x11, 0x22, 0x44, 0x88]);
+    /// A quarter of the pixels, spread evenly.
+    pub const STIPPLE_25: Pattern = Pattern([0x55, 0x00, 0x55, 0x00, 0x55, 0x00, 0x55, 0x00]);
+    /// Every other pixel.
+    pub const STIPPLE_50: Pattern = Pattern([0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA, 0x55, 0xAA]);
+    /// Three quarters of the pixels, spread evenly.
+    pub const STIPPLE_75: Pattern = Pattern([0xFF, 0xAA, 0xFF, 0xAA, 0xFF, 0xAA, 0xFF, 0xAA]);
+
+    /// A flat grey with `intensity` out of 255 of the pixels in the fill colour, so filling
+    /// white with it gives that grey level, dithered with `dither`. Error diffusion runs over
+    /// the tile with the error wrapping around its edges, so it gives the texture it gives
+    /// photos.
+    pub fn grey(intensity: u8, dither: Dither) -> Pattern {
+        Pattern(dither.tile(intensity))
+    }
+
+    /// What to write into the rows of the buffer to paint the pattern in `color`.
+    pub(crate) fn ink(&self, color: bool) -> Ink {
+        Ink {
+            value: if color {
+                self.0
+            } else {
+                self.0.map(|row| !row)
+            },
+            foreground: self.0,
+        }
+    }
+}
+
+/// The bits a pattern puts into each row of the buffer, and which of them are its
+/// foreground, for `DrawMode::Transparent`.
+#[derive(Clone, Copy)]
+pub(crate) struct Ink {
+    pub(crate) value: [u8; 8],
+    pub(crate) foreground: [u8; 8],
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Pattern::SOLID
+    }
+}
@@ -14,11 +14,11 @@ impl<T: Clone> Printer<T> {
         self.color = color;
     }
 
-    pub fn print<U>(&mut self, printable_interface: &mut U, text: &str) -> anyhow::Result<()>
+    pub fn print<U>(&mut self, printable_interface: &mut U, text: &str) -> crate::Result<()>
     where
         U: Print<T>,
     {
-        for chr in text.as_bytes().into_iter() {
+        for chr in text.as_bytes().iter() {
             printable_interface.put_char(
                 &self.cursor_position,
                 *chr as char,
@@ -0,0 +1,636 @@
+//! Scanline geometry shared by the lines, curved primitives and polygons of `MonoGraphics`.
+
+use core::mem::swap;
+
+use super::{FillRule, LineCap, Rect, Vect2D};
+
+/// Radii are capped so the midpoint criterion of [`HalfWidths`] fits in an `i64`.
+pub(crate) const MAX_RADIUS: u32 = 0x3FFF;
+
+/// `sin` of every whole degree of the first quadrant, scaled by `1 << 14`.
+const SIN_TABLE: [i16; 91] = [
+    0, 286, 572, 857, 1143, 1428, 1713, 1997, 2280, 2563, 2845, 3126, 3406, 3686, 3964, 4240, 4516,
+    4790, 5063, 5334, 5604, 5872, 6138, 6402, 6664, 6924, 7182, 7438, 7692, 7943, 8192, 8438, 8682,
+    8923, 9162, 9397, 9630, 9860, 10087, 10311, 10531, 10749, 10963, 11174, 11381, 11585, 11786,
+    11982, 12176, 12365, 12551, 12733, 12911, 13085, 13255, 13421, 13583, 13741, 13894, 14044,
+    14189, 14330, 14466, 14598, 14726, 14849, 14968, 15082, 15191, 15296, 15396, 15491, 15582,
+    15668, 15749, 15826, 15897, 15964, 16026, 16083, 16135, 16182, 16225, 16262, 16294, 16322,
+    16344, 16362, 16374, 16382, 16384,
+];
+
+/// Walks the rows of an ellipse from its centre outwards, giving the half width of each.
+///
+/// A pixel belongs to the ellipse when its centre lies within the ellipse whose radii are
+/// half a pixel longer, which is the midpoint criterion: `4x²(2b+1)² + 4y²(2a+1)²` must not
+/// exceed `(2a+1)²(2b+1)²`. As the rows are visited in order the half width only shrinks,
+/// so every row costs a few additions on average.
+pub(crate) struct HalfWidths {
+    a2: i64,
+    b2: i64,
+    limit: i64,
+    radius_y: i32,
+    width: i32,
+}
+
+impl HalfWidths {
+    pub(crate) fn new(radius_x: u32, radius_y: u32) -> Self {
+        let (a, b) = (
+            radius_x.min(MAX_RADIUS) as i64,
+            radius_y.min(MAX_RADIUS) as i64,
+        );
+        let (a2, b2) = ((2 * a + 1) * (2 * a + 1), (2 * b + 1) * (2 * b + 1));
+
+        HalfWidths {
+            a2,
+            b2,
+            limit: a2 * b2,
+            radius_y: b as i32,
+            width: a as i32,
+        }
+    }
+
+    /// Half width of row `dy`, -1 past the ellipse. `dy` must not decrease between calls.
+    pub(crate) fn at(&mut self, dy: i32) -> i32 {
+        if dy > self.radius_y {
+            return -1;
+        }
+
+        let y = dy as i64;
+
+        while self.width > 0 {
+            let x = self.width as i64;
+
+            if 4 * x * x * self.b2 + 4 * y * y * self.a2 <= self.limit {
+                break;
+            }
+
+            self.width -= 1;
+        }
+
+        self.width
+    }
+}
+
+/// Row spans of an elliptic ring, one quadrant at a time: for every row `dy` from the
+/// centre to the top, the pixels `x0..=x1` right of the centre covered by the ring.
+///
+/// The ring lies between the outline of the outer ellipse and the outline of the inner one,
+/// both included, so a ring whose inner radii equal the outer ones is the 8-connected
+/// outline of the ellipse. Without an inner ellipse the ring is filled.
+pub(crate) struct RingRows {
+    outer: HalfWidths,
+    inner: Option<HalfWidths>,
+    inner_width: i32,
+    radius_y: i32,
+    dy: i32,
+}
+
+impl RingRows {
+    pub(crate) fn new(radius_x: u32, radius_y: u32, inner: Option<(u32, u32)>) -> Self {
+        let mut inner = inner.map(|(radius_x, radius_y)| HalfWidths::new(radius_x, radius_y));
+        let inner_width = inner.as_mut().map_or(-1, |inner| inner.at(0));
+
+        RingRows {
+            outer: HalfWidths::new(radius_x, radius_y),
+            inner,
+            inner_width,
+            radius_y: radius_y.min(MAX_RADIUS) as i32,
+            dy: 0,
+        }
+    }
+}
+
+impl Iterator for RingRows {
+    /// `(dy, x0, x1)`
+    type Item = (i32, i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.dy > self.radius_y {
+            return None;
+        }
+
+        let dy = self.dy;
+        let x1 = self.outer.at(dy);
+        let x0 = match self.inner.as_mut() {
+            Some(inner) => {
+                let width = self.inner_width;
+                self.inner_width = inner.at(dy + 1);
+
+                // the outline of the inner ellipse reaches down to where the next row of it
+                // ends, so consecutive rows touch at least diagonally
+                if width < 0 {
+                    0
+                } else {
+                    (self.inner_width + 1).min(width).min(x1)
+                }
+            }
+            None => 0,
+        };
+
+        self.dy += 1;
+
+        Some((dy, x0, x1))
+    }
+}
+
+/// Direction of `degrees` clockwise from 3 o'clock on the screen, scaled by `1 << 14`.
+fn direction(degrees: i32) -> (i32, i32) {
+    let degrees = degrees.rem_euclid(360) as usize;
+    let (sin, cos) = match degrees / 90 {
+        0 => (SIN_TABLE[degrees], SIN_TABLE[90 - degrees]),
+        1 => (SIN_TABLE[180 - degrees], -SIN_TABLE[degrees - 90]),
+        2 => (-SIN_TABLE[degrees - 180], -SIN_TABLE[270 - degrees]),
+        _ => (-SIN_TABLE[360 - degrees], SIN_TABLE[degrees - 270]),
+    };
+
+    // y grows downwards, so clockwise on the screen is a positive angle
+    (cos as i32, sin as i32)
+}
+
+/// The directions swept clockwise from `start_angle` to `end_angle`, in degrees from
+/// 3 o'clock.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Sector {
+    Empty,
+    Full,
+    Partial {
+        start: (i32, i32),
+        end: (i32, i32),
+        wide: bool,
+    },
+}
+
+impl Sector {
+    pub(crate) fn new(start_angle: i32, end_angle: i32) -> Self {
+        let sweep = end_angle as i64 - start_angle as i64;
+
+        if sweep >= 360 {
+            return Sector::Full;
+        }
+
+        match sweep.rem_euclid(360) {
+            0 => Sector::Empty,
+            sweep => Sector::Partial {
+                start: direction(start_angle),
+                end: direction(end_angle),
+                wide: sweep > 180,
+            },
+        }
+    }
+
+    /// Whether the pixel at `(dx, dy)` from the centre lies within the sector.
+    pub(crate) fn contains(&self, dx: i32, dy: i32) -> bool {
+        match *self {
+            Sector::Empty => false,
+            Sector::Full => true,
+            Sector::Partial { start, end, wide } => {
+                let (dx, dy) = (dx as i64, dy as i64);
+                let after_start = start.0 as i64 * dy - start.1 as i64 * dx >= 0;
+                let before_end = end.0 as i64 * dy - end.1 as i64 * dx <= 0;
+
+                if wide {
+                    after_start || before_end
+                } else {
+                    after_start && before_end
+                }
+            }
+        }
+    }
+}
+
+/// Fixed point position of an edge crossing on a row, with 16 fractional bits.
+const ONE: i64 = 1 << 16;
+
+/// The spans of row `y` inside a polygon, from left to right, as `(x0, x1)` with `x1` not
+/// included.
+///
+/// Vertices lie on the corners of pixels and a pixel is inside when its centre is, so the
+/// polygon of a rectangle covers the same pixels as `fill_rectangle`. The crossings of the
+/// edges with the row are picked in order one at a time instead of being collected and
+/// sorted, which needs no memory at the cost of going through every edge for each of them.
+pub(crate) struct PolygonSpans<'v> {
+    vertices: &'v [Vect2D],
+    scale: i32,
+    rule: FillRule,
+    y: i32,
+    last: i64,
+    winding: i32,
+}
+
+impl<'v> PolygonSpans<'v> {
+    /// Spans of a polygon whose vertices are given in `1 / scale` pixels.
+    pub(crate) fn scaled(vertices: &'v [Vect2D], scale: i32, rule: FillRule, y: i32) -> Self {
+        PolygonSpans {
+            vertices,
+            scale,
+            rule,
+            y,
+            last: i64::MIN,
+            winding: 0,
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vect2D, Vect2D)> + 'v {
+        let vertices = self.vertices;
+
+        vertices
+            .iter()
+            .zip(vertices.iter().cycle().skip(1))
+            .map(|(from, to)| (*from, *to))
+    }
+
+    /// Where the edge crosses the centre line of the row and whether it goes down, if it
+    /// does cross it.
+    fn crossing(&self, from: Vect2D, to: Vect2D) -> Option<(i64, bool)> {
+        // twice the centre line of the row, in the units of the vertices
+        let centre = (2 * self.y as i64 + 1) * self.scale as i64;
+
+        if (2 * from.y as i64 <= centre) == (2 * to.y as i64 <= centre) {
+            return None;
+        }
+
+        let (dx, dy) = (to.x as i128 - from.x as i128, to.y as i128 - from.y as i128);
+        let rise = centre as i128 - 2 * from.y as i128;
+        let (mut x, mut den) = (
+            (2 * from.x as i128 * dy + rise * dx) * ONE as i128,
+            2 * dy * self.scale as i128,
+        );
+
+        if den < 0 {
+            (x, den) = (-x, -den);
+        }
+
+        Some((x.div_euclid(den) as i64, dy > 0))
+    }
+
+    /// The next crossing right of the last one, with the change of the winding number there.
+    fn next_crossing(&mut self) -> Option<(i64, i32)> {
+        let mut next: Option<(i64, i32)> = None;
+
+        for (from, to) in self.edges() {
+            let (x, down) = match self.crossing(from, to) {
+                Some(crossing) => crossing,
+                None => continue,
+            };
+            let winding = match (self.rule, down) {
+                (FillRule::EvenOdd, _) => 1,
+                (FillRule::NonZero, true) => 1,
+                (FillRule::NonZero, false) => -1,
+            };
+
+            next = match next {
+                _ if x <= self.last => next,
+                Some((next_x, _)) if next_x < x => next,
+                Some((next_x, next_winding)) if next_x == x => Some((x, next_winding + winding)),
+                _ => Some((x, winding)),
+            };
+        }
+
+        if let Some((x, _)) = next {
+            self.last = x;
+        }
+
+        next
+    }
+
+    fn is_inside(&self) -> bool {
+        match self.rule {
+            FillRule::EvenOdd => self.winding % 2 != 0,
+            FillRule::NonZero => self.winding != 0,
+        }
+    }
+}
+
+impl Iterator for PolygonSpans<'_> {
+    type Item = (i32, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut start = 0;
+
+        while let Some((x, winding)) = self.next_crossing() {
+            let was_inside = self.is_inside();
+            self.winding += winding;
+
+            match (was_inside, self.is_inside()) {
+                (false, true) => start = x,
+                (true, false) => {
+                    // first pixel whose centre lies in the span and the one past the last
+                    let x0 = (start - ONE / 2 + ONE - 1).div_euclid(ONE);
+                    let x1 = (x - ONE / 2 + ONE - 1).div_euclid(ONE);
+
+                    if x0 < x1 {
+                        let clamp = |x: i64| x.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+                        return Some((clamp(x0), clamp(x1)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+/// Stroke outlines are computed in sixteenths of a pixel.
+pub(crate) const SUBPIXEL: i32 = 16;
+
+/// Vertices of a stroke with round caps, which have the most.
+pub(crate) const MAX_STROKE_VERTICES: usize = 2 * (180 / ROUND_CAP_STEP as usize + 1);
+
+/// Pixels past the clip rectangle at which strokes are cut off. Far enough out that
+/// cutting them changes no pixel of any stroke ending nearer, while their outlines still
+/// fit in an `i32` in [`SUBPIXEL`] units.
+const STROKE_REACH: i32 = 1 << 24;
+
+/// Degrees between the vertices of a round cap.
+const ROUND_CAP_STEP: i32 = 15;
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    let mut x = n;
+    let mut y = (x + n / x) / 2;
+
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+
+    x
+}
+
+/// `a / b` rounded to the nearest integer, `b` being positive.
+fn div_round(a: i128, b: i128) -> i128 {
+    (2 * a + b).div_euclid(2 * b)
+}
+
+/// The part of the segment from `p1` to `p2` within `min..=max` on both axes, if it has
+/// one. Ends that are cut off are rounded to the nearest unit.
+fn clip_segment(
+    p1: (i128, i128),
+    p2: (i128, i128),
+    min: (i128, i128),
+    max: (i128, i128),
+) -> Option<((i128, i128), (i128, i128))> {
+    let d = (p2.0 - p1.0, p2.1 - p1.1);
+    // where the part starts and ends along the segment, as fractions with positive
+    // denominators
+    let (mut t0, mut t1) = ((0, 1), (1, 1));
+
+    for (p, d, min, max) in [(p1.0, d.0, min.0, max.0), (p1.1, d.1, min.1, max.1)] {
+        if d == 0 {
+            if p < min || p > max {
+                return None;
+            }
+
+            continue;
+        }
+
+        let (enter, leave) = if d > 0 {
+            ((min - p, d), (max - p, d))
+        } else {
+            ((p - max, -d), (p - min, -d))
+        };
+
+        if enter.0 * t0.1 > t0.0 * enter.1 {
+            t0 = enter;
+        }
+
+        if leave.0 * t1.1 < t1.0 * leave.1 {
+            t1 = leave;
+        }
+    }
+
+    if t0.0 * t1.1 > t1.0 * t0.1 {
+        return None;
+    }
+
+    let at = |t: (i128, i128)| {
+        (
+            p1.0 + div_round(d.0 * t.0, t.1),
+            p1.1 + div_round(d.1 * t.0, t.1),
+        )
+    };
+
+    Some((at(t0), at(t1)))
+}
+
+/// Writes the outline of a `width` pixels wide stroke from the centre of pixel `c1` to the
+/// centre of `c2` into `out`, in [`SUBPIXEL`] units, and returns how many vertices it has.
+///
+/// Butt caps end on the outer edges of both pixels, so they are covered too. The caps are
+/// part of the same outline, so no pixel of the stroke is drawn twice. A stroke of zero
+/// length lies along the x axis.
+///
+/// Ends further than [`STROKE_REACH`] from `clip` are cut off there, and there are no
+/// vertices when the whole stroke is. Strokes are at most twice [`MAX_RADIUS`] wide, like
+/// circles.
+pub(crate) fn stroke_outline(
+    c1: Vect2D,
+    c2: Vect2D,
+    width: u32,
+    cap: LineCap,
+    clip: Rect,
+    out: &mut [Vect2D; MAX_STROKE_VERTICES],
+) -> usize {
+    let width = width.min(2 * MAX_RADIUS);
+    let scale = |x: i32| x as i128 * SUBPIXEL as i128;
+    let centre = |c: Vect2D| {
+        (
+            scale(c.x) + SUBPIXEL as i128 / 2,
+            scale(c.y) + SUBPIXEL as i128 / 2,
+        )
+    };
+    let (p1, p2) = (centre(c1), centre(c2));
+    let (mut dx, mut dy) = (p2.0 - p1.0, p2.1 - p1.1);
+    let mut len = isqrt((dx * dx + dy * dy) as u128) as i128;
+
+    if len == 0 {
+        (dx, dy, len) = (1, 0, 1);
+    }
+
+    let margin = scale(STROKE_REACH);
+    let (p1, p2) = match clip_segment(
+        p1,
+        p2,
+        (scale(clip.min.x) - margin, scale(clip.min.y) - margin),
+        (scale(clip.max.x) + margin, scale(clip.max.y) + margin),
+    ) {
+        Some(ends) => ends,
+        None => return 0,
+    };
+
+    // half the width along the line and across it
+    let half = width as i128 * SUBPIXEL as i128 / 2;
+    let along = (div_round(dx * half, len), div_round(dy * half, len));
+    let across = (-along.1, along.0);
+
+    // `u` and `n` are scaled like `direction`, along and across the line, which `sign` turns
+    // around for the start
+    let vertex = |p: (i128, i128), u: i128, n: i128, sign: i128| {
+        Vect2D::new(
+            (p.0 + sign * (u * along.0 + n * across.0) / (1 << 14)) as i32,
+            (p.1 + sign * (u * along.1 + n * across.1) / (1 << 14)) as i32,
+        )
+    };
+
+    // half a pixel, in the units of `along`
+    let edge = (1 << 14) * SUBPIXEL as i128 / 2 / half.max(1);
+    let mut len = 0;
+
+    // around the end of the line from one side to the other, then around the start
+    for (p, sign) in [(p2, 1), (p1, -1)] {
+        match cap {
+            LineCap::Butt => {
+                out[len] = vertex(p, edge, -(1 << 14), sign);
+                out[len + 1] = vertex(p, edge, 1 << 14, sign);
+                len += 2;
+            }
+            LineCap::Square => {
+                out[len] = vertex(p, 1 << 14, -(1 << 14), sign);
+                out[len + 1] = vertex(p, 1 << 14, 1 << 14, sign);
+                len += 2;
+            }
+            LineCap::Round => {
+                for angle in (-90..=90).step_by(ROUND_CAP_STEP as usize) {
+                    let (cos, sin) = direction(angle);
+                    out[len] = vertex(p, cos as i128, sin as i128, sign);
+                    len += 1;
+                }
+            }
+        }
+    }
+
+    len
+}
+
+/// The Bresenham line between two pixels, stepped along its major axis from the end with
+/// the lower coordinate.
+///
+/// Step `k` of `0..=steps` lies `k` pixels from `start` along the major axis, and has moved
+/// `sign` times [`LineSteps::moves`] pixels along the minor one, so the steps within any
+/// range of coordinates are found without walking the line. Everything is computed wide
+/// enough for any `i32` endpoints.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LineSteps {
+    /// Whether the major axis is the y axis.
+    pub(crate) steep: bool,
+    /// Major and minor coordinate of step 0.
+    pub(crate) start: (i64, i64),
+    pub(crate) steps: i64,
+    pub(crate) sign: i64,
+    /// How far the minor coordinate moves over all the steps.
+    pub(crate) rise: i64,
+    bias: i64,
+}
+
+impl LineSteps {
+    pub(crate) fn new(c1: Vect2D, c2: Vect2D) -> Self {
+        let (c1, c2) = ((c1.x as i64, c1.y as i64), (c2.x as i64, c2.y as i64));
+        let steep = (c2.1 - c1.1).abs() > (c2.0 - c1.0).abs();
+        let (mut a, mut b) = if steep {
+            ((c1.1, c1.0), (c2.1, c2.0))
+        } else {
+            (c1, c2)
+        };
+
+        if a.0 > b.0 {
+            swap(&mut a, &mut b);
+        }
+
+        let steps = b.0 - a.0;
+
+        LineSteps {
+            steep,
+            start: a,
+            steps,
+            sign: if a.1 < b.1 { 1 } else { -1 },
+            rise: (b.1 - a.1).abs(),
+            bias: steps / 2,
+        }
+    }
+
+    /// How often the minor coordinate has moved by step `k`, `ceil((k * rise - bias) /
+    /// steps)`, and the error left over, which is in `0..steps`.
+    pub(crate) fn moves(&self, k: i64) -> (i64, i64) {
+        if self.steps == 0 {
+            return (0, 0);
+        }
+
+        let (steps, error) = (
+            self.steps as i128,
+            self.bias as i128 - k as i128 * self.rise as i128,
+        );
+        let moves = -error.div_euclid(steps);
+
+        (moves as i64, (error + moves * steps) as i64)
+    }
+
+    /// The first and the last step that moved `low..=high` times, the first being past the
+    /// last when there is none.
+    pub(crate) fn moved(&self, low: i64, high: i64) -> (i64, i64) {
+        if self.rise == 0 {
+            return if low <= 0 && high >= 0 {
+                (0, self.steps)
+            } else {
+                (1, 0)
+            };
+        }
+
+        let (steps, rise, bias) = (self.steps as i128, self.rise as i128, self.bias as i128);
+        let first = ((low as i128 - 1) * steps + bias).div_euclid(rise) + 1;
+        let last = (high as i128 * steps + bias).div_euclid(rise);
+
+        (
+            first.clamp(0, steps + 1) as i64,
+            last.clamp(-1, steps) as i64,
+        )
+    }
+
+    /// The moves that keep the minor coordinate within `min..max`.
+    pub(crate) fn moves_within(&self, min: i32, max: i32) -> (i64, i64) {
+        if self.sign > 0 {
+            (min as i64 - self.start.1, max as i64 - 1 - self.start.1)
+        } else {
+            (self.start.1 - (max as i64 - 1), self.start.1 - min as i64)
+        }
+    }
+
+    /// `(x, y)` of the pixel at `major` and `minor`.
+    pub(crate) fn pixel(&self, major: i64, minor: i64) -> (i64, i64) {
+        if self.steep {
+            (minor, major)
+        } else {
+            (major, minor)
+        }
+    }
+
+    /// Whether the pixel at `(x, y)` is on the line, both of its ends included.
+    pub(crate) fn contains(&self, x: i64, y: i64) -> bool {
+        let (major, minor) = if self.steep { (y, x) } else { (x, y) };
+        let (k, moved) = (major - self.start.0, self.sign * (minor - self.start.1));
+
+        (0..=self.steps).contains(&k)
+            && (0..=self.rise).contains(&moved)
+            && self.moves(k).0 == moved
+    }
+
+    /// The first and the last step whose pixel lies within `clip`, the first being past the
+    /// last when there is none.
+    pub(crate) fn steps_within(&self, clip: Rect) -> (i64, i64) {
+        let (major, minor) = if self.steep {
+            ((clip.min.y, clip.max.y), (clip.min.x, clip.max.x))
+        } else {
+            ((clip.min.x, clip.max.x), (clip.min.y, clip.max.y))
+        };
+        let (low, high) = self.moves_within(minor.0, minor.1);
+        let (first, last) = self.moved(low, high);
+
+        (
+            first.max(major.0 as i64 - self.start.0),
+            last.min(major.1 as i64 - 1 - self.start.0),
+        )
+    }
+}
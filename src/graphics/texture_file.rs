@@ -0,0 +1,266 @@
+//! The `.img` texture file format, as stored in the SPIFFS partition.
+//!
+//! A file is a 20 byte header followed by `height` rows of `stride` bytes of pixels. Every
+//! field is little endian:
+//!
+//! | Offset | Size | Field                                                               |
+//! |--------|------|---------------------------------------------------------------------|
+//! | 0      | 4    | Magic, `SMTX`                                                       |
+//! | 4      | 1    | Version, 1                                                          |
+//! | 5      | 1    | Bit order, 0 when the leftmost pixel is the least significant bit   |
+//! | 6      | 2    | Flags, bit 0 when set bits are black, bit 1 when there's a checksum |
+//! | 8      | 2    | Width in pixels                                                     |
+//! | 10     | 2    | Height in pixels                                                    |
+//! | 12     | 4    | Stride, bytes per row, at least `(width + 7) / 8`                   |
+//! | 16     | 4    | CRC-32 of the pixels, as computed by zlib, 0 without the flag       |
+//!
+//! Least significant bit first with set bits white is the layout of [`FrameBuffer`], which
+//! such files can be drawn from without a copy.
+
+use crate::Error;
+
+#[cfg(feature = "alloc")]
+use super::HeapFrameBuffer;
+use super::{stride_for, FrameBuffer, FrameBufferView};
+
+pub const TEXTURE_MAGIC: [u8; 4] = *b"SMTX";
+
+/// The version of the format this crate reads and writes.
+pub const TEXTURE_VERSION: u8 = 1;
+
+pub const TEXTURE_HEADER_LEN: usize = 20;
+
+const FLAG_INVERTED: u16 = 1 << 0;
+const FLAG_CHECKSUM: u16 = 1 << 1;
+
+/// Which bit of a byte holds its leftmost pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Bit 0, like the frame buffer and the panel.
+    #[default]
+    LsbFirst,
+    /// Bit 7, like PBM images.
+    MsbFirst,
+}
+
+/// The header of a texture file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureHeader {
+    pub width: u16,
+    pub height: u16,
+    pub stride: u32,
+    pub bit_order: BitOrder,
+    /// Whether set bits are black rather than white.
+    pub inverted: bool,
+    /// The CRC-32 of the pixels, when the file carries one.
+    pub checksum: Option<u32>,
+}
+
+impl TextureHeader {
+    /// Reads the header at the start of `bytes`, checking its fields but not the pixels.
+    pub fn parse(bytes: &[u8]) -> crate::Result<Self> {
+        if bytes.len() < TEXTURE_HEADER_LEN {
+            return Err(Error::TextureTooShort { len: bytes.len() });
+        }
+
+        if bytes[0..4] != TEXTURE_MAGIC {
+            return Err(Error::TextureMagic([
+                bytes[0], bytes[1], bytes[2], bytes[3],
+            ]));
+        }
+
+        if bytes[4] != TEXTURE_VERSION {
+            return Err(Error::TextureVersion(bytes[4]));
+        }
+
+        let bit_order = match bytes[5] {
+            0 => BitOrder::LsbFirst,
+            1 => BitOrder::MsbFirst,
+            other => return Err(Error::TextureBitOrder(other)),
+        };
+
+        let field = |offset: usize, len: usize| {
+            bytes[offset..offset + len]
+                .iter()
+                .rev()
+                .fold(0u32, |value, byte| value << 8 | *byte as u32)
+        };
+
+        let flags = field(6, 2) as u16;
+
+        if flags & !(FLAG_INVERTED | FLAG_CHECKSUM) != 0 {
+            return Err(Error::TextureFlags(flags));
+        }
+
+        if flags & FLAG_CHECKSUM == 0 && field(16, 4) != 0 {
+            return Err(Error::TextureChecksumField(field(16, 4)));
+        }
+
+        let header = TextureHeader {
+            width: field(8, 2) as u16,
+            height: field(10, 2) as u16,
+            stride: field(12, 4),
+            bit_order,
+            inverted: flags & FLAG_INVERTED != 0,
+            checksum: (flags & FLAG_CHECKSUM != 0).then(|| field(16, 4)),
+        };
+
+        if (header.stride as usize) < stride_for(header.width) {
+            return Err(Error::RowTooShort {
+                stride: header.stride as usize,
+                width: header.width,
+            });
+        }
+
+        Ok(header)
+    }
+
+    pub fn to_bytes(&self) -> [u8; TEXTURE_HEADER_LEN] {
+        let flags = if self.inverted { FLAG_INVERTED } else { 0 }
+            | if self.checksum.is_some() {
+                FLAG_CHECKSUM
+            } else {
+                0
+            };
+        let mut bytes = [0; TEXTURE_HEADER_LEN];
+
+        bytes[0..4].copy_from_slice(&TEXTURE_MAGIC);
+        bytes[4] = TEXTURE_VERSION;
+        bytes[5] = match self.bit_order {
+            BitOrder::LsbFirst => 0,
+            BitOrder::MsbFirst => 1,
+        };
+        bytes[6..8].copy_from_slice(&flags.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.width.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.height.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.stride.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.checksum.unwrap_or(0).to_le_bytes());
+
+        bytes
+    }
+
+    /// Number of bytes of pixels following the header.
+    pub fn data_len(&self) -> Option<usize> {
+        (self.stride as usize).checked_mul(self.height as usize)
+    }
+}
+
+/// A texture file whose header and pixels have been checked, borrowing its pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureFile<'a> {
+    pub header: TextureHeader,
+    pub data: &'a [u8],
+}
+
+impl<'a> TextureFile<'a> {
+    /// Checks the whole of `bytes` is a texture file: a valid header followed by exactly
+    /// the pixels it announces, matching the checksum if there is one.
+    pub fn parse(bytes: &'a [u8]) -> crate::Result<Self> {
+        let header = TextureHeader::parse(bytes)?;
+        let data = &bytes[TEXTURE_HEADER_LEN..];
+
+        let expected = header.data_len().ok_or(Error::TextureTooLarge {
+            height: header.height,
+            stride: header.stride,
+        })?;
+
+        if data.len() != expected {
+            return Err(Error::TextureLength {
+                height: header.height,
+                stride: header.stride,
+                expected,
+                len: data.len(),
+            });
+        }
+
+        if let Some(checksum) = header.checksum {
+            let actual = crc32(data);
+
+            if actual != checksum {
+                return Err(Error::TextureChecksum {
+                    actual,
+                    expected: checksum,
+                });
+            }
+        }
+
+        Ok(TextureFile { header, data })
+    }
+
+    /// The pixels as they are, when they are already in the layout of a frame buffer.
+    pub fn as_view(&self) -> Option<FrameBufferView<'a>> {
+        if self.header.bit_order != BitOrder::LsbFirst || self.header.inverted {
+            return None;
+        }
+
+        FrameBuffer::from_storage(
+            self.header.width,
+            self.header.height,
+            self.header.stride as usize,
+            self.data,
+        )
+        .ok()
+    }
+
+    /// Copies the pixels into a frame buffer, converting them to its layout.
+    #[cfg(feature = "alloc")]
+    pub fn to_frame_buffer(&self) -> HeapFrameBuffer {
+        let data = self
+            .data
+            .iter()
+            .map(|byte| {
+                let byte = match self.header.bit_order {
+                    BitOrder::LsbFirst => *byte,
+                    BitOrder::MsbFirst => byte.reverse_bits(),
+                };
+
+                if self.header.inverted {
+                    !byte
+                } else {
+                    byte
+                }
+            })
+            .collect();
+
+        FrameBuffer::from_storage(
+            self.header.width,
+            self.header.height,
+            self.header.stride as usize,
+            data,
+        )
+        .expect("the header was checked against the pixels")
+    }
+}
+
+/// Encodes `texture` as a texture file in the layout of the frame buffer, with a checksum
+/// when `checksum` is set.
+#[cfg(feature = "alloc")]
+pub fn encode_texture<S: AsRef<[u8]>>(
+    texture: &FrameBuffer<S>,
+    checksum: bool,
+) -> alloc::vec::Vec<u8> {
+    let data = texture.as_bytes();
+    let header = TextureHeader {
+        width: texture.width(),
+        height: texture.height(),
+        stride: texture.stride() as u32,
+        bit_order: BitOrder::LsbFirst,
+        inverted: false,
+        checksum: checksum.then(|| crc32(data)),
+    };
+
+    let mut bytes = alloc::vec::Vec::with_capacity(TEXTURE_HEADER_LEN + data.len());
+    bytes.extend_from_slice(&header.to_bytes());
+    bytes.extend_from_slice(data);
+
+    bytes
+}
+
+/// The CRC-32 zlib and PNG use, a bit at a time as textures are only checked on loading.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
@@ -1,3 +1,12 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod display;
+pub mod error;
+#[cfg(feature = "std")]
 pub mod filesystem;
 pub mod graphics;
+
+pub use error::{Error, Result};
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use esp_idf_svc::hal::prelude::*;
+use esp_idf_svc::hal::spi::Dma;
 use esp_idf_svc::hal::{delay::Delay, peripherals::Peripherals};
-use esp_rs_extensa::display::SharpMemoryDisplay;
+use esp_rs_extensa::display::{SharpMemoryDisplay, SharpPanel};
 use esp_rs_extensa::filesystem::register_spiffs_partition;
 use esp_rs_extensa::graphics::{Draw, MonoGraphics, Vect2D, BLACK, WHITE};
 
@@ -21,14 +22,16 @@ fn main() -> Result<()> {
     register_spiffs_partition(MOUNT_POINT, PARTITION_NAME)?;
 
     let mut display = SharpMemoryDisplay::new(
+        SharpPanel::LS027B7DH01,
         2.MHz().into(),
+        Dma::Auto(4092),
         peripherals.pins.gpio25,
         peripherals.pins.gpio26,
         peripherals.pins.gpio27,
         peripherals.spi3,
     )?;
 
-    let mut graphics = MonoGraphics::new(&mut display, 400, 240);
+    let mut graphics = MonoGraphics::from_display(&mut display);
 
     log::info!("Hello, world!");
 
@@ -0,0 +1,78 @@
+use esp_rs_extensa::display::{DisplayCall, MockDisplay};
+use esp_rs_extensa::graphics::{Draw, MonoGraphics, SetPixel, Vect2D, BLACK};
+
+#[test]
+fn first_draw_sends_whole_frame() {
+    let mut display = MockDisplay::new(64, 32);
+    MonoGraphics::new(&mut display, 64, 32).draw().unwrap();
+
+    assert!(
+        matches!(display.calls.as_slice(), [DisplayCall::Refresh(buffer)] if buffer.height() == 32)
+    );
+}
+
+#[test]
+fn unchanged_buffer_sends_nothing() {
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    graphics.draw().unwrap();
+    graphics.draw().unwrap();
+    drop(graphics);
+
+    assert_eq!(display.calls.len(), 1);
+}
+
+#[test]
+fn only_modified_rows_are_sent() {
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    graphics.draw().unwrap();
+    graphics.set_pixel(Vect2D::new(3, 5), BLACK).unwrap();
+    graphics.draw_vline(Vect2D::new(10, 20), 2, BLACK).unwrap();
+    graphics.draw().unwrap();
+    let expected = graphics.buffer.clone();
+    drop(graphics);
+
+    let calls = display.calls.split_off(1);
+    assert_eq!(
+        calls,
+        vec![DisplayCall::RefreshLines(vec![
+            (5, expected.row(5).to_vec()),
+            (20, expected.row(20).to_vec()),
+            (21, expected.row(21).to_vec()),
+        ])]
+    );
+    assert_eq!(display.frame, expected);
+}
+
+#[test]
+fn mostly_dirty_buffer_falls_back_to_full_refresh() {
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    graphics.draw().unwrap();
+    graphics
+        .fill_rectangle(Vect2D::new(0, 0), Vect2D::new(8, 20), BLACK)
+        .unwrap();
+    graphics.draw().unwrap();
+    drop(graphics);
+
+    assert!(matches!(
+        display.calls.as_slice(),
+        [_, DisplayCall::Refresh(_)]
+    ));
+}
+
+#[test]
+fn clear_display_invalidates_every_row() {
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    graphics.draw().unwrap();
+    graphics.clear_display().unwrap();
+    graphics.draw().unwrap();
+    drop(graphics);
+
+    assert!(matches!(
+        display.calls.as_slice(),
+        [_, DisplayCall::Clear, DisplayCall::Refresh(_)]
+    ));
+}
@@ -0,0 +1,113 @@
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Dimensions, Point, Size};
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Pixel;
+use esp_rs_extensa::display::MockDisplay;
+use esp_rs_extensa::graphics::{
+    Draw, HeapFrameBuffer, HeapMonoGraphics, MonoGraphics, SetPixel, Vect2D, BLACK,
+};
+
+/// Renders `paint` on a fresh white 64x32 canvas and returns its buffer.
+fn render<F>(paint: F) -> HeapFrameBuffer
+where
+    F: FnOnce(&mut HeapMonoGraphics) -> esp_rs_extensa::Result<()>,
+{
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    paint(&mut graphics).unwrap();
+    graphics.buffer.clone()
+}
+
+#[test]
+fn bounding_box_is_the_canvas() {
+    let mut display = MockDisplay::new(64, 32);
+    let graphics = MonoGraphics::new(&mut display, 64, 32);
+
+    assert_eq!(
+        graphics.bounding_box(),
+        Rectangle::new(Point::new(0, 0), Size::new(64, 32))
+    );
+}
+
+#[test]
+fn on_is_black() {
+    let expected = render(|g| {
+        g.set_pixel(Vect2D::new(3, 4), BLACK)?;
+        Draw::clear(g, false)?;
+        g.set_pixel(Vect2D::new(5, 6), true)
+    });
+
+    let actual = render(|g| {
+        g.draw_iter([Pixel(Point::new(3, 4), BinaryColor::On)])?;
+        DrawTarget::clear(g, BinaryColor::On)?;
+        g.draw_iter([Pixel(Point::new(5, 6), BinaryColor::Off)])
+    });
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn pixels_outside_the_canvas_are_skipped() {
+    let actual = render(|g| {
+        g.draw_iter([
+            Pixel(Point::new(-1, 0), BinaryColor::On),
+            Pixel(Point::new(0, -1), BinaryColor::On),
+            Pixel(Point::new(64, 0), BinaryColor::On),
+            Pixel(Point::new(0, 32), BinaryColor::On),
+            Pixel(Point::new(63, 31), BinaryColor::On),
+        ])
+    });
+
+    let expected = render(|g| g.set_pixel(Vect2D::new(63, 31), BLACK));
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn fill_solid_matches_pixel_by_pixel_fill() {
+    for x in -3..12 {
+        for width in 0..20 {
+            let area = Rectangle::new(Point::new(x, 2), Size::new(width, 3));
+
+            for color in [BinaryColor::On, BinaryColor::Off] {
+                let background = match color {
+                    BinaryColor::On => BinaryColor::Off,
+                    BinaryColor::Off => BinaryColor::On,
+                };
+
+                let fast = render(|g| {
+                    DrawTarget::clear(g, background)?;
+                    g.fill_solid(&area, color)
+                });
+                let slow = render(|g| {
+                    DrawTarget::clear(g, background)?;
+                    g.draw_iter((0..area.size.height as i32).flat_map(|dy| {
+                        (0..area.size.width as i32).map(move |dx| {
+                            Pixel(
+                                Point::new(area.top_left.x + dx, area.top_left.y + dy),
+                                color,
+                            )
+                        })
+                    }))
+                });
+
+                assert_eq!(fast, slow, "{:?} filled with {:?}", area, color);
+            }
+        }
+    }
+}
+
+#[test]
+fn fill_solid_is_clipped_to_the_canvas() {
+    let actual = render(|g| {
+        g.fill_solid(
+            &Rectangle::new(Point::new(60, 28), Size::new(10, 10)),
+            BinaryColor::On,
+        )
+    });
+
+    let expected = render(|g| g.fill_rectangle(Vect2D::new(60, 28), Vect2D::new(64, 32), BLACK));
+
+    assert_eq!(actual, expected);
+}
@@ -0,0 +1,1616 @@
+//! Golden-image tests for `MonoGraphics`.
+//!
+//! Every test draws into a `MockDisplay`, flushes the buffer and compares the frame the
+//! display received pixel-for-pixel against `tests/golden/<name>.pbm`. On a mismatch a
+//! `<name>.diff.ppm` is written next to the test binary's temporary directory, with the
+//! differing pixels marked in red. Run with `UPDATE_GOLDEN=1` to (re)generate the
+//! references after an intentional change in rendering.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use esp_rs_extensa::display::MockDisplay;
+use esp_rs_extensa::graphics::{
+    Dither, Draw, DrawMode, FillRule, FloodFillSpan, FrameBuffer, GreyImage, HeapFrameBuffer,
+    HeapMonoGraphics, LineCap, MonoGraphics, Pattern, Print, Rect, SetPixel, Vect2D, BLACK,
+    FLOOD_FILL_SPANS, MAX_CLIP_DEPTH, WHITE,
+};
+use esp_rs_extensa::Error;
+
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+const DIFF_DIR: &str = env!("CARGO_TARGET_TMPDIR");
+
+/// A rendered 1-bit frame, one `bool` per pixel with `true` meaning white.
+#[derive(PartialEq, Eq)]
+struct Frame {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Frame {
+    fn from_buffer(buffer: &HeapFrameBuffer) -> Self {
+        let width = buffer.width() as usize;
+        let pixels = buffer
+            .rows()
+            .flat_map(|line| (0..width).map(move |x| line[x / 8] & (1 << (x % 8)) != 0))
+            .collect();
+
+        Frame {
+            width,
+            height: buffer.height() as usize,
+            pixels,
+        }
+    }
+
+    /// Encodes the frame as a binary (P4) PBM, where set bits are black.
+    fn to_pbm(&self) -> Vec<u8> {
+        let mut out = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+
+        for row in self.pixels.chunks(self.width) {
+            for byte in row.chunks(8) {
+                out.push(
+                    byte.iter()
+                        .enumerate()
+                        .fold(0, |acc, (i, white)| acc | ((!white as u8) << (7 - i))),
+                );
+            }
+        }
+
+        out
+    }
+
+    fn from_pbm(data: &[u8]) -> Self {
+        let mut fields = Vec::new();
+        let mut pos = 0;
+
+        while fields.len() < 3 {
+            while data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+
+            if data[pos] == b'#' {
+                while data[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+
+            let start = pos;
+            while !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            fields.push(std::str::from_utf8(&data[start..pos]).unwrap());
+        }
+
+        assert_eq!(fields[0], "P4", "golden images must be binary PBM files");
+        let width: usize = fields[1].parse().unwrap();
+        let height: usize = fields[2].parse().unwrap();
+        let stride = (width + 7) / 8;
+        let raster = &data[pos + 1..];
+
+        assert_eq!(raster.len(), stride * height, "truncated PBM raster");
+
+        let pixels = raster
+            .chunks(stride)
+            .flat_map(|row| (0..width).map(move |x| row[x / 8] & (0x80 >> (x % 8)) == 0))
+            .collect();
+
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Encodes a colour (P6) PPM showing `self` with pixels that differ from `other` in red.
+    fn diff_ppm(&self, other: &Frame) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+
+        for (actual, expected) in self.pixels.iter().zip(other.pixels.iter()) {
+            let rgb: [u8; 3] = match (actual, expected) {
+                _ if actual != expected => [0xFF, 0x00, 0x00],
+                (true, _) => [0xFF, 0xFF, 0xFF],
+                (false, _) => [0x60, 0x60, 0x60],
+            };
+            out.extend(rgb);
+        }
+
+        out
+    }
+}
+
+/// Runs `paint` on a fresh white `width` x `height` canvas and returns the flushed frame.
+fn render<F>(width: u16, height: u16, paint: F) -> Frame
+where
+    F: FnOnce(&mut HeapMonoGraphics) -> esp_rs_extensa::Result<()>,
+{
+    let mut display = MockDisplay::new(width, height);
+
+    {
+        let mut graphics = MonoGraphics::new(&mut display, width, height);
+        paint(&mut graphics).expect("drawing failed");
+        graphics.draw().expect("flushing to the display failed");
+    }
+
+    let buffer = display
+        .last_refresh()
+        .expect("nothing was sent to the display");
+    Frame::from_buffer(buffer)
+}
+
+fn assert_golden(name: &str, frame: &Frame) {
+    let golden_path = PathBuf::from(GOLDEN_DIR).join(format!("{}.pbm", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(GOLDEN_DIR).unwrap();
+        fs::write(&golden_path, frame.to_pbm()).unwrap();
+        return;
+    }
+
+    let data = fs::read(&golden_path).unwrap_or_else(|_| {
+        panic!(
+            "missing golden image {}, run with UPDATE_GOLDEN=1 to create it",
+            golden_path.display()
+        )
+    });
+    let expected = Frame::from_pbm(&data);
+
+    assert_eq!(
+        (frame.width, frame.height),
+        (expected.width, expected.height),
+        "{}: frame size differs from the golden image",
+        name
+    );
+
+    if *frame != expected {
+        let mismatched = frame
+            .pixels
+            .iter()
+            .zip(expected.pixels.iter())
+            .filter(|(a, e)| a != e)
+            .count();
+        let diff_path = PathBuf::from(DIFF_DIR).join(format!("{}.diff.ppm", name));
+        fs::write(&diff_path, frame.diff_ppm(&expected)).unwrap();
+
+        panic!(
+            "{}: {} pixels differ from {}, see {}",
+            name,
+            mismatched,
+            golden_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn clear_black() {
+    let frame = render(64, 32, |g| g.clear(BLACK));
+    assert_golden("clear_black", &frame);
+}
+
+#[test]
+fn set_pixel_pattern() {
+    let frame = render(64, 32, |g| {
+        for i in 0..32 {
+            g.set_pixel(Vect2D::new(i * 2, i), BLACK)?;
+            g.set_pixel(Vect2D::new(63 - i, i), BLACK)?;
+        }
+        Ok(())
+    });
+    assert_golden("set_pixel_pattern", &frame);
+}
+
+#[test]
+fn set_pixel_white_on_black() {
+    let frame = render(64, 32, |g| {
+        g.clear(BLACK)?;
+        for i in 0..8 {
+            g.set_pixel(Vect2D::new(i * 9, i * 4), WHITE)?;
+        }
+        Ok(())
+    });
+    assert_golden("set_pixel_white_on_black", &frame);
+}
+
+#[test]
+fn draw_line_octants() {
+    let frame = render(64, 64, |g| {
+        let centre = Vect2D::new(32, 32);
+        for end in [
+            Vect2D::new(63, 40),
+            Vect2D::new(40, 63),
+            Vect2D::new(24, 63),
+            Vect2D::new(0, 40),
+            Vect2D::new(0, 24),
+            Vect2D::new(24, 0),
+            Vect2D::new(40, 0),
+            Vect2D::new(63, 24),
+        ] {
+            g.draw_line(centre, end, BLACK)?;
+        }
+        Ok(())
+    });
+    assert_golden("draw_line_octants", &frame);
+}
+
+#[test]
+fn draw_hline_alignments() {
+    // Every start offset within a byte, with lengths that stay inside the first byte,
+    // end exactly on a byte boundary and span several whole bytes.
+    let frame = render(64, 48, |g| {
+        for offset in 0..8 {
+            g.draw_hline(Vect2D::new(offset, offset * 2), 3, BLACK)?;
+            g.draw_hline(
+                Vect2D::new(8 + offset, 16 + offset * 2),
+                8 - offset as u32,
+                BLACK,
+            )?;
+            g.draw_hline(
+                Vect2D::new(offset, 32 + offset * 2),
+                40 + offset as u32,
+                BLACK,
+            )?;
+        }
+        Ok(())
+    });
+    assert_golden("draw_hline_alignments", &frame);
+}
+
+#[test]
+fn draw_hline_white_on_black() {
+    let frame = render(64, 48, |g| {
+        g.clear(BLACK)?;
+        for offset in 0..8 {
+            g.draw_hline(Vect2D::new(offset, offset * 2), 3, WHITE)?;
+            g.draw_hline(
+                Vect2D::new(8 + offset, 16 + offset * 2),
+                8 - offset as u32,
+                WHITE,
+            )?;
+            g.draw_hline(
+                Vect2D::new(offset, 32 + offset * 2),
+                40 + offset as u32,
+                WHITE,
+            )?;
+        }
+        Ok(())
+    });
+    assert_golden("draw_hline_white_on_black", &frame);
+}
+
+#[test]
+fn draw_vline_columns() {
+    let frame = render(64, 32, |g| {
+        for i in 0..16 {
+            g.draw_vline(Vect2D::new(i * 4, i), 16, BLACK)?;
+        }
+        Ok(())
+    });
+    assert_golden("draw_vline_columns", &frame);
+}
+
+#[test]
+fn draw_rectangle_nested() {
+    let frame = render(64, 48, |g| {
+        for i in (0..16).step_by(4) {
+            g.draw_rectangle(
+                Vect2D::new(4 + i, 4 + i),
+                Vect2D::new(59 - i, 43 - i),
+                BLACK,
+            )?;
+        }
+        Ok(())
+    });
+    assert_golden("draw_rectangle_nested", &frame);
+}
+
+#[test]
+fn fill_rectangle_unaligned() {
+    let frame = render(64, 48, |g| {
+        g.fill_rectangle(Vect2D::new(3, 2), Vect2D::new(29, 20), BLACK)?;
+        g.fill_rectangle(Vect2D::new(34, 2), Vect2D::new(61, 45), BLACK)?;
+        g.fill_rectangle(Vect2D::new(40, 10), Vect2D::new(53, 30), WHITE)?;
+        Ok(())
+    });
+    assert_golden("fill_rectangle_unaligned", &frame);
+}
+
+#[test]
+fn rectangles_with_swapped_corners() {
+    let corners = [
+        (Vect2D::new(29, 20), Vect2D::new(3, 2)),
+        (Vect2D::new(61, 2), Vect2D::new(34, 45)),
+        (Vect2D::new(8, 45), Vect2D::new(26, 26)),
+    ];
+
+    let swapped = render(64, 48, |g| {
+        g.fill_rectangle(corners[0].0, corners[0].1, BLACK)?;
+        g.draw_rectangle(corners[1].0, corners[1].1, BLACK)?;
+        g.fill_rectangle(corners[2].0, corners[2].1, BLACK)?;
+        g.draw_rectangle(corners[2].0, corners[2].1, BLACK)
+    });
+    let ordered = render(64, 48, |g| {
+        g.fill_rectangle(Vect2D::new(3, 2), Vect2D::new(29, 20), BLACK)?;
+        g.draw_rectangle(Vect2D::new(34, 2), Vect2D::new(61, 45), BLACK)?;
+        g.fill_rectangle(Vect2D::new(8, 26), Vect2D::new(26, 45), BLACK)?;
+        g.draw_rectangle(Vect2D::new(8, 26), Vect2D::new(26, 45), BLACK)
+    });
+
+    assert!(swapped == ordered);
+    assert_golden("rectangles_with_swapped_corners", &swapped);
+}
+
+#[test]
+fn draw_texture_checker() {
+    let data: Vec<u8> = (0..16)
+        .flat_map(|row| [if row % 2 == 0 { 0xAA } else { 0x55 }; 4])
+        .collect();
+    let texture = FrameBuffer::from_storage(32, 16, 4, data).unwrap();
+    let frame = render(64, 32, |g| {
+        g.draw_texture(Vect2D::new(0, 0), texture.view())
+    });
+    assert_golden("draw_texture_checker", &frame);
+}
+
+#[test]
+fn draw_texture_from_flash_land() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/spiffs/land.img");
+    let frame = render(400, 240, |g| {
+        g.draw_texture_from_flash(Vect2D::new(0, 0), path)
+    });
+    assert_golden("draw_texture_from_flash_land", &frame);
+}
+
+#[test]
+fn put_char_ascii() {
+    let frame = render(128, 32, |g| {
+        for (i, chr) in "Hello, 123!".chars().enumerate() {
+            g.put_char(&Vect2D::new(i as i32 * 6, 2), chr, BLACK)?;
+        }
+        g.fill_rectangle(Vect2D::new(0, 14), Vect2D::new(128, 26), BLACK)?;
+        for (i, chr) in "Inverse".chars().enumerate() {
+            g.put_char(&Vect2D::new(2 + i as i32 * 6, 16), chr, WHITE)?;
+        }
+        Ok(())
+    });
+    assert_golden("put_char_ascii", &frame);
+}
+
+#[test]
+fn put_char_beyond_the_font() {
+    let text = |text: &'static str| {
+        render(64, 16, move |g| {
+            for (i, chr) in text.chars().enumerate() {
+                g.put_char(&Vect2D::new(2 + i as i32 * 6, 4), chr, BLACK)?;
+            }
+            Ok(())
+        })
+    };
+
+    assert!(text("5\u{20ac} \u{1f600}\u{ff}") == text("5? ?\u{ff}"));
+}
+
+#[test]
+fn clipped_at_canvas_edges() {
+    let data: Vec<u8> = (0..16)
+        .flat_map(|row| [if row % 2 == 0 { 0xAA } else { 0x55 }; 2])
+        .collect();
+    let texture = FrameBuffer::from_storage(16, 16, 2, data).unwrap();
+
+    let frame = render(64, 32, |g| {
+        g.set_pixel(Vect2D::new(-1, 5), BLACK)?;
+        g.set_pixel(Vect2D::new(64, 5), BLACK)?;
+        g.draw_line(Vect2D::new(-20, -10), Vect2D::new(80, 40), BLACK)?;
+        g.draw_hline(Vect2D::new(-5, 2), 20, BLACK)?;
+        g.draw_hline(Vect2D::new(50, 4), 100, BLACK)?;
+        g.draw_vline(Vect2D::new(60, -4), 10, BLACK)?;
+        g.draw_rectangle(Vect2D::new(-4, 20), Vect2D::new(10, 40), BLACK)?;
+        g.fill_rectangle(Vect2D::new(56, 24), Vect2D::new(70, 40), BLACK)?;
+        g.draw_texture(Vect2D::new(-5, -6), texture.view())?;
+        g.draw_texture(Vect2D::new(24, 20), texture.view())?;
+        g.put_char(&Vect2D::new(-3, 26), 'A', BLACK)?;
+        g.put_char(&Vect2D::new(40, -4), 'B', BLACK)
+    });
+    assert_golden("clipped_at_canvas_edges", &frame);
+}
+
+#[test]
+fn clipped_to_clip_rect() {
+    let frame = render(64, 32, |g| {
+        g.set_clip(Rect::new(Vect2D::new(10, 6), Vect2D::new(45, 26)));
+        g.clear(WHITE)?;
+        g.fill_rectangle(Vect2D::new(0, 0), Vect2D::new(64, 32), BLACK)?;
+        g.reset_clip();
+        g.draw_line(Vect2D::new(0, 0), Vect2D::new(63, 31), BLACK)?;
+        g.set_clip(Rect::new(Vect2D::new(13, 9), Vect2D::new(42, 23)));
+        g.fill_rectangle(Vect2D::new(0, 0), Vect2D::new(64, 32), WHITE)?;
+        g.draw_line(Vect2D::new(0, 31), Vect2D::new(63, 0), BLACK)?;
+        for (i, chr) in "clip".chars().enumerate() {
+            g.put_char(&Vect2D::new(8 + i as i32 * 6, 12), chr, BLACK)?;
+        }
+        Ok(())
+    });
+    assert_golden("clipped_to_clip_rect", &frame);
+}
+
+#[test]
+fn extreme_coordinates_are_clipped() {
+    let (min, max) = (i32::MIN, i32::MAX);
+    let frame = render(64, 32, |g| {
+        g.draw_line(Vect2D::new(min, 3), Vect2D::new(max, 3), BLACK)?;
+        g.draw_line(Vect2D::new(5, max), Vect2D::new(5, min), BLACK)?;
+        g.draw_line(Vect2D::new(min, min), Vect2D::new(max, max), BLACK)?;
+        g.draw_vline(Vect2D::new(-1, 0), 32, BLACK)?;
+        g.draw_vline(Vect2D::new(64, 0), 32, BLACK)?;
+        g.draw_vline(Vect2D::new(max, max), u32::MAX, BLACK)?;
+        g.draw_rectangle(Vect2D::new(min, min), Vect2D::new(max, max), BLACK)?;
+        g.fill_rectangle(Vect2D::new(20, 20), Vect2D::new(max, max), BLACK)
+    });
+    let expected = render(64, 32, |g| {
+        g.draw_hline(Vect2D::new(0, 3), 64, BLACK)?;
+        g.draw_vline(Vect2D::new(5, 0), 32, BLACK)?;
+        g.draw_line(Vect2D::new(-1, -1), Vect2D::new(40, 40), BLACK)?;
+        g.fill_rectangle(Vect2D::new(20, 20), Vect2D::new(64, 32), BLACK)
+    });
+
+    assert!(frame == expected);
+}
+
+#[test]
+fn nested_clips() {
+    let data: Vec<u8> = (0..16)
+        .flat_map(|row| [if row % 2 == 0 { 0xAA } else { 0x55 }; 4])
+        .collect();
+    let texture = FrameBuffer::from_storage(32, 16, 4, data).unwrap();
+    let frame = render(64, 32, |g| {
+        g.push_clip(Rect::new(Vect2D::new(4, 4), Vect2D::new(40, 28)))?;
+        g.clear(BLACK)?;
+        g.push_clip(Rect::new(Vect2D::new(20, 0), Vect2D::new(64, 20)))?;
+        g.draw_texture(Vect2D::new(13, 1), texture.view())?;
+        g.draw_line(Vect2D::new(0, 31), Vect2D::new(63, 0), WHITE)?;
+        for (i, chr) in "nest".chars().enumerate() {
+            g.put_char(&Vect2D::new(18 + i as i32 * 6, 16), chr, WHITE)?;
+        }
+        g.pop_clip()?;
+        g.draw_rectangle(Vect2D::new(0, 0), Vect2D::new(63, 31), WHITE)?;
+        g.pop_clip()?;
+        g.draw_line(Vect2D::new(0, 0), Vect2D::new(63, 31), BLACK)
+    });
+    assert_golden("nested_clips", &frame);
+}
+
+#[test]
+fn clip_stack_restores_outer_clip() {
+    let mut display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 32);
+    let canvas = graphics.clip();
+    let outer = Rect::new(Vect2D::new(-8, 4), Vect2D::new(40, 28));
+    let inner = Rect::new(Vect2D::new(20, 0), Vect2D::new(80, 20));
+
+    assert!(graphics.pop_clip().is_err());
+
+    graphics.push_clip(outer).unwrap();
+    assert_eq!(graphics.clip(), outer.intersect(&canvas));
+    graphics.push_clip(inner).unwrap();
+    assert_eq!(
+        graphics.clip(),
+        Rect::new(Vect2D::new(20, 4), Vect2D::new(40, 20))
+    );
+    graphics.pop_clip().unwrap();
+    assert_eq!(graphics.clip(), outer.intersect(&canvas));
+    graphics.pop_clip().unwrap();
+    assert_eq!(graphics.clip(), canvas);
+
+    for _ in 0..MAX_CLIP_DEPTH {
+        graphics.push_clip(inner).unwrap();
+    }
+    assert!(graphics.push_clip(inner).is_err());
+    graphics.reset_clip();
+    assert_eq!(graphics.clip(), canvas);
+    assert!(graphics.pop_clip().is_err());
+}
+
+#[test]
+fn circles_and_ellipses() {
+    let frame = render(96, 48, |g| {
+        for radius in 0..6 {
+            g.draw_circle(Vect2D::new(4 + radius as i32 * 8, 6), radius, BLACK)?;
+            g.fill_circle(Vect2D::new(4 + radius as i32 * 8, 18), radius, BLACK)?;
+        }
+        g.draw_circle(Vect2D::new(70, 16), 15, BLACK)?;
+        g.fill_circle(Vect2D::new(70, 16), 9, BLACK)?;
+        g.fill_circle(Vect2D::new(70, 16), 4, WHITE)?;
+        g.draw_ellipse(Vect2D::new(20, 37), 19, 8, BLACK)?;
+        g.fill_ellipse(Vect2D::new(20, 37), 12, 4, BLACK)?;
+        g.fill_ellipse(Vect2D::new(50, 38), 3, 9, BLACK)?;
+        g.draw_ellipse(Vect2D::new(90, 40), 12, 12, BLACK)
+    });
+    assert_golden("circles_and_ellipses", &frame);
+}
+
+#[test]
+fn arcs() {
+    let frame = render(96, 48, |g| {
+        g.draw_arc(Vect2D::new(20, 20), 18, 135, 45, 3, BLACK)?;
+        g.draw_arc(Vect2D::new(20, 20), 12, 0, 360, 1, BLACK)?;
+        g.draw_arc(Vect2D::new(20, 20), 10, -90, 30, 20, BLACK)?;
+        g.draw_arc(Vect2D::new(60, 20), 16, 200, 340, 1, BLACK)?;
+        g.draw_arc(Vect2D::new(60, 24), 10, 10, 170, 4, BLACK)?;
+        g.draw_arc(Vect2D::new(84, 38), 20, 180, 270, 6, BLACK)?;
+        g.draw_arc(Vect2D::new(84, 38), 6, 45, 45, 6, BLACK)
+    });
+    assert_golden("arcs", &frame);
+}
+
+#[test]
+fn circles_match_their_outline() {
+    for radius in 0..20 {
+        let center = Vect2D::new(24, 24);
+        let filled = render(48, 48, |g| g.fill_circle(center, radius, BLACK));
+        let outline = render(48, 48, |g| g.draw_circle(center, radius, BLACK));
+        let full_arc = render(48, 48, |g| g.draw_arc(center, radius, 90, 450, 1, BLACK));
+        let pie = render(48, 48, |g| {
+            g.draw_arc(center, radius, 0, 360, radius + 1, BLACK)
+        });
+
+        assert!(outline == full_arc, "radius {}", radius);
+        assert!(filled == pie, "radius {}", radius);
+        for y in 0..48 {
+            for x in 0..48 {
+                let (dx, dy) = (x as i32 - center.x, y as i32 - center.y);
+                let inside = dx * dx + dy * dy <= (radius * radius + radius) as i32;
+                let pixel = y * 48 + x;
+                assert_eq!(!filled.pixels[pixel], inside, "radius {}", radius);
+                assert!(filled.pixels[pixel] || !outline.pixels[pixel] || inside);
+            }
+        }
+    }
+}
+
+#[test]
+fn triangles_and_polygons() {
+    let star = |x: i32, y: i32| {
+        [(16, 0), (26, 30), (0, 11), (32, 11), (6, 30)].map(|(dx, dy)| Vect2D::new(x + dx, y + dy))
+    };
+    let arrow = [
+        (0, 6),
+        (12, 6),
+        (12, 0),
+        (22, 10),
+        (12, 20),
+        (12, 14),
+        (0, 14),
+    ]
+    .map(|(x, y)| Vect2D::new(x + 70, y + 26));
+    let frame = render(96, 48, |g| {
+        g.fill_polygon(&star(0, 0), FillRule::EvenOdd, BLACK)?;
+        g.fill_polygon(&star(34, 0), FillRule::NonZero, BLACK)?;
+        g.draw_polygon(&star(64, 0), BLACK)?;
+        g.fill_triangle(
+            Vect2D::new(2, 47),
+            Vect2D::new(20, 33),
+            Vect2D::new(40, 44),
+            BLACK,
+        )?;
+        g.draw_triangle(
+            Vect2D::new(44, 46),
+            Vect2D::new(50, 33),
+            Vect2D::new(66, 40),
+            BLACK,
+        )?;
+        g.fill_polygon(&arrow, FillRule::NonZero, BLACK)
+    });
+    assert_golden("triangles_and_polygons", &frame);
+}
+
+#[test]
+fn polygons_at_extreme_coordinates() {
+    let (min, max) = (i32::MIN, i32::MAX);
+    let corners = [(min, 3), (max, 3), (0, max)].map(|(x, y)| Vect2D::new(x, y));
+    let shapes: [(Frame, Frame); 2] = [
+        (
+            render(64, 32, |g| g.draw_hline(Vect2D::new(0, 3), 64, BLACK)),
+            render(64, 32, |g| g.draw_polygon(&corners, BLACK)),
+        ),
+        (
+            render(64, 32, |g| {
+                g.fill_rectangle(Vect2D::new(0, 3), Vect2D::new(64, 32), BLACK)
+            }),
+            render(64, 32, |g| {
+                g.fill_polygon(&corners, FillRule::NonZero, BLACK)
+            }),
+        ),
+    ];
+
+    for (i, (expected, actual)) in shapes.iter().enumerate() {
+        assert!(expected == actual, "shape {}", i);
+    }
+}
+
+#[test]
+fn polygons_tile_without_overlap() {
+    let corners = [(3, 2), (29, 2), (29, 21), (3, 21)].map(|(x, y)| Vect2D::new(x, y));
+    let [a, b, c, d] = corners;
+
+    let rectangle = render(32, 24, |g| g.fill_rectangle(a, c, BLACK));
+    let polygon = render(32, 24, |g| {
+        g.fill_polygon(&corners, FillRule::EvenOdd, BLACK)
+    });
+    let upper = render(32, 24, |g| g.fill_triangle(a, b, c, BLACK));
+    let lower = render(32, 24, |g| g.fill_triangle(c, d, a, BLACK));
+
+    assert!(polygon == rectangle);
+    for pixel in 0..32 * 24 {
+        assert!(upper.pixels[pixel] || lower.pixels[pixel]);
+        assert_eq!(
+            upper.pixels[pixel] && lower.pixels[pixel],
+            rectangle.pixels[pixel]
+        );
+    }
+}
+
+#[test]
+fn round_rects() {
+    let frame = render(96, 48, |g| {
+        for (i, radius) in [0, 2, 4, 8, 30].into_iter().enumerate() {
+            let x = i as i32 * 19;
+            g.draw_round_rect(Vect2D::new(x, 0), Vect2D::new(x + 17, 20), radius, BLACK)?;
+            g.fill_round_rect(Vect2D::new(x, 24), Vect2D::new(x + 18, 40), radius, BLACK)?;
+        }
+        g.fill_round_rect(Vect2D::new(4, 28), Vect2D::new(14, 37), 2, WHITE)?;
+        g.draw_round_rect(Vect2D::new(60, 42), Vect2D::new(100, 47), 6, BLACK)
+    });
+    assert_golden("round_rects", &frame);
+}
+
+#[test]
+fn thick_lines() {
+    let frame = render(96, 48, |g| {
+        for (i, cap) in [LineCap::Butt, LineCap::Square, LineCap::Round]
+            .into_iter()
+            .enumerate()
+        {
+            let y = 6 + i as i32 * 14;
+            g.draw_thick_line(Vect2D::new(8, y), Vect2D::new(30, y), 7, cap, BLACK)?;
+            g.draw_thick_line(
+                Vect2D::new(40, y - 3),
+                Vect2D::new(56, y + 4),
+                5,
+                cap,
+                BLACK,
+            )?;
+            g.draw_thick_line(
+                Vect2D::new(66, y + 4),
+                Vect2D::new(72, y - 4),
+                3,
+                cap,
+                BLACK,
+            )?;
+            g.draw_thick_line(Vect2D::new(84, y), Vect2D::new(84, y), 8, cap, BLACK)?;
+        }
+        g.draw_thick_line(
+            Vect2D::new(-10, 44),
+            Vect2D::new(120, 40),
+            2,
+            LineCap::Butt,
+            BLACK,
+        )
+    });
+    assert_golden("thick_lines", &frame);
+}
+
+#[test]
+fn round_rects_and_thick_lines_match_simpler_shapes() {
+    let (c1, c2) = (Vect2D::new(3, 2), Vect2D::new(29, 21));
+    let (min, max) = (
+        Vect2D::new(i32::MIN, i32::MIN),
+        Vect2D::new(i32::MAX, i32::MAX),
+    );
+    let shapes: [(Frame, Frame); 11] = [
+        (
+            render(48, 48, |g| g.draw_rectangle(c1, c2, BLACK)),
+            render(48, 48, |g| g.draw_round_rect(c1, c2, 0, BLACK)),
+        ),
+        (
+            render(48, 48, |g| g.fill_rectangle(c1, c2, BLACK)),
+            render(48, 48, |g| g.fill_round_rect(c1, c2, 0, BLACK)),
+        ),
+        (
+            render(48, 48, |g| g.draw_circle(Vect2D::new(20, 20), 14, BLACK)),
+            render(48, 48, |g| {
+                g.draw_round_rect(Vect2D::new(6, 6), Vect2D::new(34, 34), 20, BLACK)
+            }),
+        ),
+        (
+            render(48, 48, |g| g.fill_circle(Vect2D::new(20, 20), 14, BLACK)),
+            render(48, 48, |g| {
+                g.fill_round_rect(Vect2D::new(6, 6), Vect2D::new(35, 35), 14, BLACK)
+            }),
+        ),
+        (
+            render(48, 48, |g| g.draw_hline(c1, 27, BLACK)),
+            render(48, 48, |g| {
+                g.draw_thick_line(c1, Vect2D::new(29, 2), 1, LineCap::Butt, BLACK)
+            }),
+        ),
+        (
+            render(48, 48, |g| {
+                g.fill_rectangle(Vect2D::new(3, 10), Vect2D::new(4, 15), BLACK)
+            }),
+            render(48, 48, |g| {
+                g.draw_thick_line(
+                    Vect2D::new(3, 12),
+                    Vect2D::new(3, 12),
+                    5,
+                    LineCap::Butt,
+                    BLACK,
+                )
+            }),
+        ),
+        (
+            render(48, 48, |_| Ok(())),
+            render(48, 48, |g| g.draw_round_rect(min, max, 5, BLACK)),
+        ),
+        (
+            render(48, 48, |g| g.clear(BLACK)),
+            render(48, 48, |g| g.fill_round_rect(min, max, u32::MAX, BLACK)),
+        ),
+        (
+            render(48, 48, |g| {
+                g.fill_rectangle(Vect2D::new(0, 7), Vect2D::new(41, 10), BLACK)
+            }),
+            render(48, 48, |g| {
+                g.draw_thick_line(
+                    Vect2D::new(min.x, 8),
+                    Vect2D::new(40, 8),
+                    3,
+                    LineCap::Butt,
+                    BLACK,
+                )
+            }),
+        ),
+        (
+            render(48, 48, |g| {
+                g.fill_rectangle(Vect2D::new(5, 0), Vect2D::new(10, 48), BLACK)
+            }),
+            render(48, 48, |g| {
+                g.draw_thick_line(
+                    Vect2D::new(7, max.y),
+                    Vect2D::new(7, min.y),
+                    5,
+                    LineCap::Round,
+                    BLACK,
+                )
+            }),
+        ),
+        (
+            render(48, 48, |g| g.clear(BLACK)),
+            render(48, 48, |g| {
+                g.draw_thick_line(min, max, u32::MAX, LineCap::Square, BLACK)
+            }),
+        ),
+    ];
+
+    for (i, (expected, actual)) in shapes.iter().enumerate() {
+        assert!(expected == actual, "shape {}", i);
+    }
+}
+
+#[test]
+fn pattern_fills() {
+    let frame = render(96, 48, |g| {
+        let patterns = [
+            Pattern::SOLID,
+            Pattern::CHECKER,
+            Pattern::HATCH,
+            Pattern::STIPPLE_25,
+            Pattern::STIPPLE_50,
+            Pattern::STIPPLE_75,
+        ];
+
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            g.set_fill_pattern(pattern);
+            g.fill_rectangle(
+                Vect2D::new(i as i32 * 16, 0),
+                Vect2D::new(i as i32 * 16 + 13, 13),
+                BLACK,
+            )?;
+        }
+
+        g.set_fill_pattern(Pattern::STIPPLE_50);
+        g.fill_circle(Vect2D::new(12, 30), 11, BLACK)?;
+        g.draw_circle(Vect2D::new(12, 30), 11, BLACK)?;
+        g.set_fill_pattern(Pattern::HATCH);
+        g.fill_round_rect(Vect2D::new(28, 18), Vect2D::new(58, 44), 6, BLACK)?;
+        g.draw_round_rect(Vect2D::new(28, 18), Vect2D::new(57, 43), 6, BLACK)?;
+        g.set_fill_pattern(Pattern::STIPPLE_25);
+        g.fill_triangle(
+            Vect2D::new(62, 46),
+            Vect2D::new(78, 16),
+            Vect2D::new(94, 46),
+            WHITE,
+        )?;
+        g.fill_rectangle(Vect2D::new(76, 40), Vect2D::new(96, 48), BLACK)
+    });
+    assert_golden("pattern_fills", &frame);
+}
+
+/// Paints a few overlapping outlines to flood, with areas open to each other through gaps.
+fn flood_scene(g: &mut HeapMonoGraphics) -> esp_rs_extensa::Result<()> {
+    g.draw_circle(Vect2D::new(20, 20), 16, BLACK)?;
+    g.draw_circle(Vect2D::new(20, 20), 6, BLACK)?;
+    g.draw_rectangle(Vect2D::new(30, 4), Vect2D::new(60, 40), BLACK)?;
+    g.draw_line(Vect2D::new(30, 40), Vect2D::new(60, 4), BLACK)?;
+    g.draw_arc(Vect2D::new(75, 24), 14, 30, 330, 2, BLACK)?;
+    g.set_pixel(Vect2D::new(45, 22), WHITE)?;
+    for x in (34..56).step_by(4) {
+        g.draw_vline(Vect2D::new(x, 30), 10, BLACK)?;
+    }
+    Ok(())
+}
+
+/// The area `flood_fill` should cover, found pixel by pixel.
+fn flooded(frame: &Frame, seed: (usize, usize), clip: Rect) -> Vec<bool> {
+    let color = frame.pixels[seed.1 * frame.width + seed.0];
+    let mut area = vec![false; frame.pixels.len()];
+    let mut todo = vec![seed];
+
+    while let Some((x, y)) = todo.pop() {
+        let index = y * frame.width + x;
+
+        if !clip.contains(Vect2D::new(x as i32, y as i32))
+            || area[index]
+            || frame.pixels[index] != color
+        {
+            continue;
+        }
+
+        area[index] = true;
+        todo.extend([(x + 1, y), (x, y + 1)]);
+        if x > 0 {
+            todo.push((x - 1, y));
+        }
+        if y > 0 {
+            todo.push((x, y - 1));
+        }
+    }
+
+    area
+}
+
+#[test]
+fn flood_fill() {
+    let frame = render(96, 48, |g| {
+        flood_scene(g)?;
+        g.flood_fill(Vect2D::new(20, 20), BLACK)?;
+        g.flood_fill(Vect2D::new(12, 20), BLACK)?;
+        g.flood_fill(Vect2D::new(40, 10), BLACK)?;
+        g.push_clip(Rect::new(Vect2D::new(64, 0), Vect2D::new(96, 30)))?;
+        g.flood_fill(Vect2D::new(75, 24), BLACK)?;
+        g.pop_clip()
+    });
+    assert_golden("flood_fill", &frame);
+}
+
+#[test]
+fn flood_fill_covers_the_connected_area() {
+    let scene = render(96, 48, flood_scene);
+    let canvas = Rect::new(Vect2D::new(0, 0), Vect2D::new(96, 48));
+    let clip = Rect::new(Vect2D::new(10, 6), Vect2D::new(70, 35));
+
+    for (seed, clip) in [
+        ((1, 1), canvas),
+        ((20, 20), canvas),
+        ((12, 20), canvas),
+        ((40, 10), canvas),
+        ((50, 30), canvas),
+        ((35, 38), canvas),
+        ((75, 24), canvas),
+        ((30, 20), canvas),
+        ((12, 20), clip),
+        ((1, 1), clip),
+    ] {
+        let area = flooded(&scene, seed, clip);
+        let color = !scene.pixels[seed.1 * 96 + seed.0];
+        let filled = render(96, 48, |g| {
+            flood_scene(g)?;
+            g.set_clip(clip);
+            g.flood_fill(Vect2D::new(seed.0 as i32, seed.1 as i32), color)
+        });
+
+        for (pixel, filled) in filled.pixels.iter().enumerate() {
+            let expected = if area[pixel] {
+                color
+            } else {
+                scene.pixels[pixel]
+            };
+            assert_eq!(*filled, expected, "seed {:?}", seed);
+        }
+    }
+}
+
+/// Checks flooding `scene` from `seed` with room for `spans` row spans covers exactly the
+/// area it should, and returns how long the fill took.
+fn assert_floods<F>(
+    width: u16,
+    height: u16,
+    scene: F,
+    seed: (usize, usize),
+    spans: usize,
+) -> Duration
+where
+    F: Fn(&mut HeapMonoGraphics) -> esp_rs_extensa::Result<()>,
+{
+    let drawn = render(width, height, &scene);
+    let canvas = Rect::new(Vect2D::new(0, 0), Vect2D::new(width as i32, height as i32));
+    let area = flooded(&drawn, seed, canvas);
+    let color = !drawn.pixels[seed.1 * drawn.width + seed.0];
+    let mut spans = vec![FloodFillSpan::default(); spans];
+    let mut took = Duration::ZERO;
+    let filled = render(width, height, |g| {
+        scene(g)?;
+        let start = Instant::now();
+        g.flood_fill_with_storage(Vect2D::new(seed.0 as i32, seed.1 as i32), color, &mut spans)?;
+        took = start.elapsed();
+        Ok(())
+    });
+
+    for (pixel, filled) in filled.pixels.iter().enumerate() {
+        let expected = if area[pixel] {
+            color
+        } else {
+            drawn.pixels[pixel]
+        };
+        assert_eq!(*filled, expected, "pixel {} from seed {:?}", pixel, seed);
+    }
+
+    took
+}
+
+#[test]
+fn flood_fill_of_ragged_areas() {
+    // a comb with teeth one pixel apart
+    assert_floods(
+        400,
+        16,
+        |g| {
+            for x in (1..400).step_by(2) {
+                g.draw_vline(Vect2D::new(x, 0), 15, BLACK)?;
+            }
+            Ok(())
+        },
+        (0, 15),
+        FLOOD_FILL_SPANS,
+    );
+
+    // dots every other pixel, leaving more spans than `FLOOD_FILL_SPANS`, and with room
+    // for only two the rest is walked pixel by pixel
+    let lattice = |width: i32, height: i32| {
+        move |g: &mut HeapMonoGraphics| {
+            for y in (1..height).step_by(2) {
+                for x in (1..width).step_by(2) {
+                    g.set_pixel(Vect2D::new(x, y), BLACK)?;
+                }
+            }
+            Ok(())
+        }
+    };
+    assert_floods(400, 64, lattice(400, 64), (0, 0), FLOOD_FILL_SPANS);
+    assert_floods(48, 16, lattice(48, 16), (0, 0), 2);
+
+    assert_floods(
+        400,
+        240,
+        |g| {
+            for line in 0..10 {
+                for (i, chr) in "The quick brown fox jumps over the lazy dog, 0123456789!"
+                    .chars()
+                    .enumerate()
+                {
+                    g.put_char(&Vect2D::new(2 + i as i32 * 6, 4 + line * 12), chr, BLACK)?;
+                }
+            }
+            Ok(())
+        },
+        (0, 0),
+        FLOOD_FILL_SPANS,
+    );
+
+    assert_floods(
+        400,
+        240,
+        |g| {
+            for i in 0..30 {
+                g.draw_circle(Vect2D::new(8 + i * 13, 120), 4, BLACK)?;
+            }
+            Ok(())
+        },
+        (0, 0),
+        FLOOD_FILL_SPANS,
+    );
+}
+
+/// Carves a maze of one pixel wide passages out of a black canvas, all of them reachable
+/// from the top left corner and most ending in a dead end.
+fn maze(g: &mut HeapMonoGraphics, width: i32, height: i32) -> esp_rs_extensa::Result<()> {
+    let mut state = 0x9e37_79b9u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+    let (columns, rows) = ((width - 1) / 2, (height - 1) / 2);
+    let mut visited = vec![false; (columns * rows) as usize];
+    let mut path = vec![(0, 0)];
+
+    g.clear(BLACK)?;
+    g.set_pixel(Vect2D::new(1, 1), WHITE)?;
+    visited[0] = true;
+
+    while let Some(&(x, y)) = path.last() {
+        let next: Vec<(i32, i32)> = [(1, 0), (0, 1), (-1, 0), (0, -1)]
+            .into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| x >= 0 && x < columns && y >= 0 && y < rows)
+            .filter(|&(x, y)| !visited[(y * columns + x) as usize])
+            .collect();
+
+        if next.is_empty() {
+            path.pop();
+            continue;
+        }
+
+        let (nx, ny) = next[random() as usize % next.len()];
+        visited[(ny * columns + nx) as usize] = true;
+        g.set_pixel(Vect2D::new(x + nx + 1, y + ny + 1), WHITE)?;
+        g.set_pixel(Vect2D::new(2 * nx + 1, 2 * ny + 1), WHITE)?;
+        path.push((nx, ny));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn flood_fill_of_a_maze() {
+    // the dead ends leave more spans to come back to than the default storage holds
+    let filled = render(400, 240, |g| {
+        maze(g, 400, 240)?;
+        g.flood_fill(Vect2D::new(1, 1), BLACK)
+    });
+    assert!(filled.pixels.iter().all(|pixel| *pixel == BLACK));
+
+    assert_floods(400, 240, |g| maze(g, 400, 240), (1, 1), 2);
+}
+
+#[test]
+fn flood_fill_of_noise() {
+    let mut state = 0x2545_f491u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    for _ in 0..300 {
+        let density = random() % 60;
+        let mut dots = Vec::new();
+        for y in 0..30 {
+            for x in 0..40 {
+                if random() % 100 < density {
+                    dots.push(Vect2D::new(x, y));
+                }
+            }
+        }
+        let seed = ((random() % 40) as usize, (random() % 30) as usize);
+
+        assert_floods(
+            40,
+            30,
+            |g| {
+                for dot in &dots {
+                    g.set_pixel(*dot, BLACK)?;
+                }
+                Ok(())
+            },
+            seed,
+            2,
+        );
+    }
+}
+
+#[test]
+fn flood_fill_of_a_large_noisy_scene_is_quick() {
+    let mut state = 0x1234_5678u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    for density in [5, 20] {
+        let mut dots = Vec::new();
+        for y in 0..240 {
+            for x in 0..400 {
+                if random() % 100 < density {
+                    dots.push(Vect2D::new(x, y));
+                }
+            }
+        }
+
+        for (seed, spans) in [
+            ((0, 0), 2),
+            ((0, 0), FLOOD_FILL_SPANS),
+            ((200, 120), FLOOD_FILL_SPANS),
+        ] {
+            let took = assert_floods(
+                400,
+                240,
+                |g| {
+                    for dot in &dots {
+                        g.set_pixel(*dot, BLACK)?;
+                    }
+                    g.set_pixel(Vect2D::new(seed.0 as i32, seed.1 as i32), WHITE)
+                },
+                seed,
+                spans,
+            );
+
+            // walking the area pixel by pixel once took half a minute here
+            assert!(
+                took < Duration::from_secs(2),
+                "{}% noise from {:?} with {} spans took {:?}",
+                density,
+                seed,
+                spans,
+                took
+            );
+        }
+    }
+}
+
+const DRAW_MODES: [DrawMode; 6] = [
+    DrawMode::Replace,
+    DrawMode::Or,
+    DrawMode::And,
+    DrawMode::Xor,
+    DrawMode::Invert,
+    DrawMode::Transparent,
+];
+
+/// A 16x12 texture of a black diamond on white.
+fn diamond() -> HeapFrameBuffer {
+    let data = (0..12)
+        .flat_map(|y: i32| {
+            let row = (0..16).fold(0u16, |row, x: i32| {
+                let white = (2 * x - 15).abs() + (2 * y - 11).abs() > 12;
+                row | (white as u16) << x
+            });
+            row.to_le_bytes()
+        })
+        .collect();
+
+    FrameBuffer::from_storage(16, 12, 2, data).unwrap()
+}
+
+#[test]
+fn draw_modes() {
+    let texture = diamond();
+
+    // a band for each mode, black on the left and white on the right
+    let frame = render(96, 64, |g| {
+        for (i, mode) in DRAW_MODES.into_iter().enumerate() {
+            let x = i as i32 * 16;
+
+            g.set_draw_mode(DrawMode::Replace);
+            g.set_fill_pattern(Pattern::SOLID);
+            g.fill_rectangle(Vect2D::new(x, 0), Vect2D::new(x + 8, 64), BLACK)?;
+
+            g.set_draw_mode(mode);
+            g.set_fill_pattern(Pattern::CHECKER);
+            g.fill_rectangle(Vect2D::new(x + 2, 2), Vect2D::new(x + 14, 14), WHITE)?;
+            g.put_char(&Vect2D::new(x + 2, 18), 'A', WHITE)?;
+            g.put_char(&Vect2D::new(x + 8, 18), 'B', BLACK)?;
+            g.draw_texture(Vect2D::new(x, 30), texture.view())?;
+            g.set_fill_pattern(Pattern::SOLID);
+            g.fill_circle(Vect2D::new(x + 8, 54), 6, WHITE)?;
+            g.draw_hline(Vect2D::new(x + 1, 62), 14, BLACK)?;
+        }
+
+        Ok(())
+    });
+    assert_golden("draw_modes", &frame);
+}
+
+/// Draws one of every kind of shape, none overlapping another, with text and textures at
+/// and off byte boundaries.
+fn mode_scene(g: &mut HeapMonoGraphics, color: bool) -> esp_rs_extensa::Result<()> {
+    let texture = diamond();
+
+    g.set_pixel(Vect2D::new(2, 2), color)?;
+    g.draw_line(Vect2D::new(24, 22), Vect2D::new(44, 31), color)?;
+    g.draw_rectangle(Vect2D::new(4, 4), Vect2D::new(30, 20), color)?;
+    g.draw_rectangle(Vect2D::new(34, 4), Vect2D::new(34, 12), color)?;
+    g.draw_rectangle(Vect2D::new(36, 4), Vect2D::new(44, 4), color)?;
+    g.fill_rectangle(Vect2D::new(6, 24), Vect2D::new(20, 30), color)?;
+    g.draw_thick_line(
+        Vect2D::new(50, 6),
+        Vect2D::new(70, 20),
+        5,
+        LineCap::Round,
+        color,
+    )?;
+    g.draw_round_rect(Vect2D::new(48, 24), Vect2D::new(70, 44), 5, color)?;
+    g.fill_round_rect(Vect2D::new(52, 28), Vect2D::new(66, 40), 3, color)?;
+    g.draw_circle(Vect2D::new(84, 12), 9, color)?;
+    g.fill_ellipse(Vect2D::new(84, 12), 5, 3, color)?;
+    g.draw_arc(Vect2D::new(84, 36), 9, 45, 300, 3, color)?;
+    g.draw_triangle(
+        Vect2D::new(100, 4),
+        Vect2D::new(120, 10),
+        Vect2D::new(104, 24),
+        color,
+    )?;
+    g.fill_polygon(
+        &[
+            Vect2D::new(100, 30),
+            Vect2D::new(120, 28),
+            Vect2D::new(110, 46),
+        ],
+        FillRule::EvenOdd,
+        color,
+    )?;
+    g.draw_texture(Vect2D::new(24, 34), texture.view())?;
+    g.draw_texture(Vect2D::new(3, 34), texture.view())?;
+    g.put_char(&Vect2D::new(122, 4), 'x', color)?;
+    g.put_char(&Vect2D::new(121, 20), 'y', color)
+}
+
+/// Outlines whose edges share pixels, next to the vertices or where they cross.
+fn outline_scene(g: &mut HeapMonoGraphics, color: bool) -> esp_rs_extensa::Result<()> {
+    g.draw_triangle(
+        Vect2D::new(2, 10),
+        Vect2D::new(20, 5),
+        Vect2D::new(20, 15),
+        color,
+    )?;
+    g.draw_polygon(
+        &[
+            Vect2D::new(30, 4),
+            Vect2D::new(52, 8),
+            Vect2D::new(31, 12),
+            Vect2D::new(50, 20),
+        ],
+        color,
+    )?;
+    g.draw_polygon(
+        &[(76, 2), (86, 32), (60, 13), (92, 13), (66, 32)].map(|(x, y)| Vect2D::new(x, y)),
+        color,
+    )?;
+    g.draw_polygon(
+        &[(100, 4), (124, 4), (110, 4), (110, 30), (104, 18)].map(|(x, y)| Vect2D::new(x, y)),
+        color,
+    )
+}
+
+#[test]
+fn xor_draws_every_pixel_once() {
+    // white on black inverts every pixel drawn, so any drawn twice would stay black
+    for scene in [mode_scene, outline_scene] {
+        let replaced = render(128, 48, |g| {
+            g.clear(BLACK)?;
+            scene(g, WHITE)
+        });
+        let inverted = render(128, 48, |g| {
+            g.clear(BLACK)?;
+            g.set_draw_mode(DrawMode::Xor);
+            scene(g, WHITE)
+        });
+
+        assert!(inverted == replaced);
+    }
+}
+
+#[test]
+fn outlines_with_many_vertices_are_quick() {
+    // a fine sawtooth along the canvas, with hundreds of edges on every row it covers
+    let vertices: Vec<Vect2D> = (0..1024)
+        .map(|i| Vect2D::new(4 + i * 390 / 1023, 100 + i % 2 * 40))
+        .collect();
+    let replaced = render(400, 240, |g| {
+        g.clear(BLACK)?;
+        g.draw_polygon(&vertices, WHITE)
+    });
+
+    let start = Instant::now();
+    let inverted = render(400, 240, |g| {
+        g.clear(BLACK)?;
+        g.set_draw_mode(DrawMode::Xor);
+        g.draw_polygon(&vertices, WHITE)
+    });
+
+    // going through every edge for each run of pixels on a row once took seconds here
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(inverted == replaced);
+}
+
+#[test]
+fn outlines_include_every_vertex() {
+    let frame = render(128, 48, |g| outline_scene(g, BLACK));
+
+    for (x, y) in [
+        (2, 10),
+        (20, 5),
+        (20, 15),
+        (30, 4),
+        (52, 8),
+        (31, 12),
+        (50, 20),
+    ] {
+        assert!(!frame.pixels[y * frame.width + x], "vertex ({}, {})", x, y);
+    }
+
+    assert!(frame.pixels[10 * frame.width + 1]);
+}
+
+#[test]
+fn xor_and_invert_undo_themselves() {
+    let scene = render(128, 48, flood_scene);
+
+    for mode in [DrawMode::Xor, DrawMode::Invert] {
+        for color in [WHITE, BLACK] {
+            let frame = render(128, 48, |g| {
+                flood_scene(g)?;
+                g.set_draw_mode(mode);
+                g.set_fill_pattern(Pattern::HATCH);
+                mode_scene(g, color)?;
+                mode_scene(g, color)
+            });
+
+            assert!(frame == scene, "{:?} with {}", mode, color);
+        }
+    }
+}
+
+#[test]
+fn transparent_black_only_darkens() {
+    // only the glyphs, the set bits of patterns and the black of textures are drawn, which
+    // is what anding black does
+    let [transparent, anded] = [DrawMode::Transparent, DrawMode::And].map(|mode| {
+        render(128, 48, |g| {
+            flood_scene(g)?;
+            g.fill_rectangle(Vect2D::new(0, 0), Vect2D::new(64, 48), BLACK)?;
+            g.set_draw_mode(mode);
+            g.set_fill_pattern(Pattern::STIPPLE_50);
+            mode_scene(g, BLACK)
+        })
+    });
+
+    assert!(transparent == anded);
+}
+
+const DITHERS: [Dither; 3] = [Dither::Bayer, Dither::FloydSteinberg, Dither::Atkinson];
+
+/// A `width` x `height` greyscale image with every pixel at `intensity(x, y)`.
+fn grey_pixels(width: usize, height: usize, intensity: impl Fn(usize, usize) -> u8) -> Vec<u8> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| intensity(x, y))
+        .collect()
+}
+
+#[test]
+fn grey_images() {
+    // a gradient from black to white, over which the bottom half fades towards mid grey
+    let pixels = grey_pixels(128, 24, |x, y| {
+        let level = (x * 255 / 127) as i32;
+        (level + (128 - level) * y.saturating_sub(12) as i32 / 12) as u8
+    });
+    let image = GreyImage::from_storage(128, 24, 128, &pixels).unwrap();
+
+    let mut errors = vec![0; Dither::FloydSteinberg.error_len(128)];
+
+    let frame = render(128, 96, |g| {
+        for (i, dither) in DITHERS.into_iter().enumerate() {
+            g.draw_grey_image(Vect2D::new(0, i as i32 * 24), image, dither, &mut errors)?;
+        }
+
+        for (i, level) in [0, 32, 64, 96, 128, 160, 192, 224, 255]
+            .into_iter()
+            .enumerate()
+        {
+            g.set_fill_pattern(Pattern::grey(level, Dither::Bayer));
+            g.fill_rectangle(
+                Vect2D::new(i as i32 * 14, 76),
+                Vect2D::new(i as i32 * 14 + 12, 86),
+                WHITE,
+            )?;
+            g.set_fill_pattern(Pattern::grey(level, Dither::FloydSteinberg));
+            g.fill_circle(Vect2D::new(i as i32 * 14 + 6, 91), 4, WHITE)?;
+        }
+
+        Ok(())
+    });
+    assert_golden("grey_images", &frame);
+}
+
+#[test]
+fn bayer_grey_patterns_are_nested() {
+    let mut previous = Pattern::grey(0, Dither::Bayer);
+    assert_eq!(previous, Pattern([0x00; 8]));
+
+    // every level lights the pixels of the one below and a quarter of a pixel more
+    for level in 1..=255u8 {
+        let pattern = Pattern::grey(level, Dither::Bayer);
+        let lit: u32 = pattern.0.iter().map(|row| row.count_ones()).sum();
+
+        assert_eq!(lit, (level as u32 * 64 + 127) / 255, "level {}", level);
+
+        for (row, below) in pattern.0.iter().zip(previous.0) {
+            assert_eq!(row & below, below, "level {}", level);
+        }
+
+        previous = pattern;
+    }
+
+    assert_eq!(previous, Pattern::SOLID);
+}
+
+#[test]
+fn dithering_keeps_the_grey_level() {
+    let cases = [
+        (Dither::Bayer, &[16, 64, 100, 128, 200, 250][..]),
+        (Dither::FloydSteinberg, &[16, 64, 100, 128, 200, 250]),
+        // Atkinson drops a quarter of the error, which pushes every grey but the middle one
+        // towards black or white
+        (Dither::Atkinson, &[128]),
+    ];
+
+    for (dither, levels) in cases {
+        for &level in levels {
+            let pixels = grey_pixels(64, 64, |_, _| level);
+            let image = GreyImage::from_storage(64, 64, 64, &pixels).unwrap();
+            let mut errors = vec![0; dither.error_len(64)];
+            let frame = render(64, 64, |g| {
+                g.draw_grey_image(Vect2D::new(0, 0), image, dither, &mut errors)
+            });
+            let white = frame.pixels.iter().filter(|pixel| **pixel).count();
+            let expected = 64 * 64 * level as usize / 255;
+
+            assert!(
+                white.abs_diff(expected) <= 64 * 64 / 50,
+                "{:?} at {}: {} white pixels, expected about {}",
+                dither,
+                level,
+                white,
+                expected
+            );
+
+            let tile = Pattern::grey(level, dither);
+            let lit: u32 = tile.0.iter().map(|row| row.count_ones()).sum();
+
+            assert!(
+                lit.abs_diff(level as u32 * 64 / 255) <= 2,
+                "{:?} pattern at {}: {} lit",
+                dither,
+                level,
+                lit
+            );
+        }
+    }
+}
+
+#[test]
+fn clipping_leaves_dithering_unchanged() {
+    let pixels = grey_pixels(60, 40, |x, y| ((x * 7 + y * 13) % 256) as u8);
+    let image = GreyImage::from_storage(60, 40, 60, &pixels).unwrap();
+    let clip = Rect::new(Vect2D::new(20, 16), Vect2D::new(50, 40));
+
+    for dither in DITHERS {
+        let mut errors = vec![0; dither.error_len(60)];
+        let whole = render(64, 48, |g| {
+            g.draw_grey_image(Vect2D::new(-3, 5), image, dither, &mut errors)
+        });
+        let clipped = render(64, 48, |g| {
+            g.set_clip(clip);
+            g.draw_grey_image(Vect2D::new(-3, 5), image, dither, &mut errors)
+        });
+
+        for y in 0..48 {
+            for x in 0..64 {
+                let pixel = y * 64 + x;
+                let expected = if clip.contains(Vect2D::new(x as i32, y as i32)) {
+                    whole.pixels[pixel]
+                } else {
+                    WHITE
+                };
+
+                assert_eq!(
+                    clipped.pixels[pixel], expected,
+                    "{:?} at {}, {}",
+                    dither, x, y
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn dithering_into_a_buffer_matches_drawing() {
+    let pixels = grey_pixels(44, 20, |x, y| ((x * 11 + y * 5) % 256) as u8);
+    let image = GreyImage::from_storage(44, 20, 44, &pixels).unwrap();
+
+    for dither in DITHERS {
+        let buffer = image.dither(dither).unwrap();
+        let mut errors = vec![0; dither.error_len(44)];
+        let frame = render(44, 20, |g| {
+            g.draw_grey_image(Vect2D::new(0, 0), image, dither, &mut errors)
+        });
+
+        assert!(Frame::from_buffer(&buffer) == frame, "{:?}", dither);
+    }
+}
+
+#[test]
+fn dithering_wide_images() {
+    let width = 612;
+    let pixels = grey_pixels(width, 8, |x, _| (x * 255 / width) as u8);
+    let image = GreyImage::from_storage(width as u16, 8, width, &pixels).unwrap();
+
+    for dither in DITHERS {
+        let frame = Frame::from_buffer(&image.dither(dither).unwrap());
+        let lit = frame.pixels.iter().filter(|white| **white).count();
+
+        assert_eq!(frame.width, width);
+        assert!(
+            lit.abs_diff(width * 8 / 2) < width / 4,
+            "{:?} lit {}",
+            dither,
+            lit
+        );
+
+        let mut errors = vec![0; dither.error_len(width as u16)];
+        let drawn = render(width as u16, 8, |g| {
+            g.draw_grey_image(Vect2D::new(0, 0), image, dither, &mut errors)
+        });
+
+        assert!(drawn == frame, "{:?}", dither);
+    }
+}
+
+#[test]
+fn malformed_grey_images_are_rejected() {
+    let pixels = vec![128; 100];
+
+    assert!(GreyImage::from_storage(10, 10, 10, &pixels[..99]).is_err());
+    assert!(GreyImage::from_storage(10, 10, 8, &pixels[..80]).is_err());
+
+    let image = GreyImage::from_storage(10, 10, 10, &pixels).unwrap();
+    let mut display = MockDisplay::new(64, 16);
+    let mut graphics = MonoGraphics::new(&mut display, 64, 16);
+    let mut errors = vec![0; Dither::Atkinson.error_len(10)];
+
+    // too little storage for the error is rejected even when nothing would be drawn
+    assert!(matches!(
+        graphics.draw_grey_image(
+            Vect2D::new(-20, 0),
+            image,
+            Dither::Atkinson,
+            &mut errors[1..]
+        ),
+        Err(Error::DitherStorage {
+            len: 38,
+            needed: 39
+        })
+    ));
+    assert!(graphics
+        .draw_grey_image(Vect2D::new(0, 0), image, Dither::Atkinson, &mut errors)
+        .is_ok());
+    assert!(graphics
+        .draw_grey_image(Vect2D::new(0, 0), image, Dither::Bayer, &mut [])
+        .is_ok());
+}
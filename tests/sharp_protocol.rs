@@ -0,0 +1,311 @@
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::rc::Rc;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::spi::{self, Operation, SpiBus, SpiDevice};
+use esp_rs_extensa::display::{ActiveHighCs, Display, SharpEncoder, SharpPanel, SharpSpiDisplay};
+use esp_rs_extensa::graphics::FrameBuffer;
+
+/// `SpiDevice` recording the bytes of every transaction.
+#[derive(Default)]
+struct RecordingDevice {
+    transactions: Vec<Vec<u8>>,
+}
+
+impl spi::ErrorType for RecordingDevice {
+    type Error = Infallible;
+}
+
+impl SpiDevice for RecordingDevice {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        let mut bytes = Vec::new();
+
+        for operation in operations {
+            if let Operation::Write(words) = operation {
+                bytes.extend_from_slice(words);
+            }
+        }
+
+        self.transactions.push(bytes);
+
+        Ok(())
+    }
+}
+
+fn line(panel: SharpPanel, byte: u8) -> Vec<u8> {
+    vec![byte; panel.line_bytes()]
+}
+
+#[test]
+fn every_row_of_every_panel_is_addressed() {
+    for panel in SharpPanel::ALL {
+        let mut encoder = SharpEncoder::new(panel);
+        let mut out = vec![0; panel.frame_bytes()];
+        let data = line(panel, 0xFF);
+
+        for row in [0, panel.height() - 1] {
+            assert!(encoder
+                .encode_lines(&mut out, [(row, data.as_slice())])
+                .is_ok());
+        }
+
+        assert!(encoder
+            .encode_lines(&mut out, [(panel.height(), data.as_slice())])
+            .is_err());
+    }
+}
+
+#[test]
+fn eight_bit_write_command() {
+    let panel = SharpPanel::LS027B7DH01;
+    let mut encoder = SharpEncoder::new(panel);
+    let mut out = vec![0xEE; panel.frame_bytes()];
+    let (a, b) = (line(panel, 0xA5), line(panel, 0x3C));
+
+    let len = encoder
+        .encode_lines(&mut out, [(0, a.as_slice()), (9, b.as_slice())])
+        .unwrap();
+
+    let mut expected = vec![0x01, 1];
+    expected.extend(&a);
+    expected.extend([0x00, 10]);
+    expected.extend(&b);
+    expected.extend([0x00, 0x00]);
+    assert_eq!(&out[..len], expected.as_slice());
+}
+
+#[test]
+fn ten_bit_write_command() {
+    let panel = SharpPanel::LS032B7DD02;
+    let mut encoder = SharpEncoder::new(panel);
+    let mut out = vec![0xEE; panel.frame_bytes()];
+    let (a, b) = (line(panel, 0xFF), line(panel, 0x81));
+
+    let len = encoder
+        .encode_lines(&mut out, [(5, a.as_slice()), (534, b.as_slice())])
+        .unwrap();
+
+    // address 6 and 535, the two low bits share the byte with the mode
+    let mut expected = vec![0x01 | (2 << 6), 1];
+    expected.extend(&a);
+    expected.extend([3 << 6, 133]);
+    expected.extend(&b);
+    expected.extend([0x00; 4]);
+    assert_eq!(&out[..len], expected.as_slice());
+}
+
+#[test]
+fn vcom_alternates_between_commands() {
+    let panel = SharpPanel::LS013B7DH05;
+    let mut encoder = SharpEncoder::new(panel);
+    let mut out = vec![0x00; panel.frame_bytes()];
+    let data = line(panel, 0x00);
+
+    let mut modes = Vec::new();
+    assert_eq!(encoder.encode_clear(&mut out).unwrap(), 2);
+    modes.push(out[..2].to_vec());
+    assert_eq!(encoder.encode_vcom(&mut out).unwrap(), 2);
+    modes.push(out[..2].to_vec());
+    encoder
+        .encode_lines(&mut out, [(0, data.as_slice())])
+        .unwrap();
+    modes.push(out[..2].to_vec());
+    assert_eq!(encoder.encode_vcom(&mut out).unwrap(), 2);
+    modes.push(out[..2].to_vec());
+
+    assert_eq!(
+        modes,
+        vec![
+            vec![0x04, 0x00],
+            vec![0x02, 0x00],
+            vec![0x01, 0x01],
+            vec![0x02, 0x00],
+        ]
+    );
+}
+
+#[test]
+fn invalid_lines_are_rejected() {
+    let panel = SharpPanel::LS013B7DH05;
+    let mut encoder = SharpEncoder::new(panel);
+    let mut out = vec![0x00; panel.frame_bytes()];
+    let data = line(panel, 0x00);
+
+    assert!(encoder
+        .encode_lines(&mut out, [(panel.height(), data.as_slice())])
+        .is_err());
+    assert!(encoder.encode_lines(&mut out, [(0, &data[1..])]).is_err());
+    assert!(encoder
+        .encode_lines(
+            &mut out[..panel.command_bytes(1) - 1],
+            [(0, data.as_slice())]
+        )
+        .is_err());
+}
+
+#[test]
+fn driver_sends_a_frame_in_one_transaction() {
+    let panel = SharpPanel::LS006B7DH03;
+    let mut display =
+        SharpSpiDisplay::new(panel, RecordingDevice::default(), [0u8; 64 * 10 + 2]).unwrap();
+    let frame = FrameBuffer::new(panel.width(), panel.height());
+
+    display.refresh(frame.view()).unwrap();
+
+    let (device, _) = display.release();
+    assert_eq!(device.transactions.len(), 1);
+    assert_eq!(device.transactions[0].len(), panel.frame_bytes());
+    assert_eq!(&device.transactions[0][..3], &[0x01, 1, 0xFF]);
+}
+
+#[test]
+fn short_buffer_splits_the_frame() {
+    let panel = SharpPanel::LS006B7DH03;
+    let mut display = SharpSpiDisplay::new(
+        panel,
+        RecordingDevice::default(),
+        vec![0u8; panel.command_bytes(3)],
+    )
+    .unwrap();
+    let frame = FrameBuffer::new(panel.width(), panel.height());
+
+    display.refresh(frame.view()).unwrap();
+
+    let (device, _) = display.release();
+    let rows: Vec<usize> = device
+        .transactions
+        .iter()
+        .map(|command| (command.len() - 2) / (2 + panel.line_bytes()))
+        .collect();
+    assert_eq!(rows.len(), 22);
+    assert!(rows[..21].iter().all(|rows| *rows == 3));
+    assert_eq!(rows[21], 1);
+
+    // every command toggles VCOM and restarts addressing where the last one stopped
+    assert_eq!(&device.transactions[0][..2], &[0x01, 1]);
+    assert_eq!(&device.transactions[1][..2], &[0x03, 4]);
+    assert_eq!(&device.transactions[21][..2], &[0x03, 64]);
+}
+
+#[test]
+fn buffer_too_short_for_a_row_is_rejected() {
+    let panel = SharpPanel::LS027B7DH01;
+    let display = SharpSpiDisplay::new(panel, RecordingDevice::default(), [0u8; 16]);
+
+    assert!(display.is_err());
+}
+
+#[test]
+fn msb_first_reverses_every_byte() {
+    let panel = SharpPanel::LS006B7DH03;
+    let mut display = SharpSpiDisplay::new(panel, RecordingDevice::default(), [0u8; 32])
+        .unwrap()
+        .msb_first();
+    let mut data = line(panel, 0x00);
+    data[0] = 0x01;
+
+    display.refresh_line(2, &data).unwrap();
+
+    let (device, _) = display.release();
+    assert_eq!(&device.transactions[0][..3], &[0x80, 0xC0, 0x80]);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BusEvent {
+    Cs(bool),
+    Delay(u32),
+    Write(Vec<u8>),
+    Flush,
+}
+
+type Events = Rc<RefCell<Vec<BusEvent>>>;
+
+struct RecordingBus(Events);
+
+impl spi::ErrorType for RecordingBus {
+    type Error = Infallible;
+}
+
+impl SpiBus for RecordingBus {
+    // The panels have no data out, so reading only ever sees an idle line.
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        words.fill(0x00);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(BusEvent::Write(words.to_vec()));
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Infallible> {
+        self.write(write)?;
+        self.read(read)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Infallible> {
+        self.write(words)?;
+        self.read(words)
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(BusEvent::Flush);
+        Ok(())
+    }
+}
+
+struct RecordingPin(Events);
+
+impl digital::ErrorType for RecordingPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for RecordingPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(BusEvent::Cs(false));
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.0.borrow_mut().push(BusEvent::Cs(true));
+        Ok(())
+    }
+}
+
+struct RecordingDelay(Events);
+
+impl DelayNs for RecordingDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().push(BusEvent::Delay(ns));
+    }
+}
+
+#[test]
+fn active_high_cs_frames_the_transfer() {
+    let events = Events::default();
+    let device = ActiveHighCs::new(
+        RecordingBus(events.clone()),
+        RecordingPin(events.clone()),
+        RecordingDelay(events.clone()),
+    )
+    .unwrap();
+    let mut display = SharpSpiDisplay::new(SharpPanel::LS006B7DH03, device, [0u8; 32]).unwrap();
+
+    display.clear_display().unwrap();
+    drop(display);
+
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            BusEvent::Cs(false),
+            BusEvent::Cs(true),
+            BusEvent::Delay(3_000),
+            BusEvent::Write(vec![0x04, 0x00]),
+            BusEvent::Flush,
+            BusEvent::Delay(1_000),
+            BusEvent::Cs(false),
+        ]
+    );
+}
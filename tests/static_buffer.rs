@@ -0,0 +1,53 @@
+use esp_rs_extensa::display::{DisplayCall, MockDisplay};
+use esp_rs_extensa::graphics::{
+    frame_buffer_len, Draw, FrameBuffer, MonoGraphics, StaticMonoGraphics, Vect2D, BLACK,
+};
+
+fn paint<G: Draw<bool>>(graphics: &mut G) -> esp_rs_extensa::Result<()> {
+    graphics.draw_rectangle(Vect2D::new(4, 4), Vect2D::new(59, 27), BLACK)?;
+    graphics.fill_rectangle(Vect2D::new(10, 10), Vect2D::new(30, 20), BLACK)?;
+    graphics.draw_line(Vect2D::new(0, 31), Vect2D::new(63, 0), BLACK)
+}
+
+#[test]
+fn static_buffer_renders_like_heap_buffer() {
+    let mut heap_display = MockDisplay::new(64, 32);
+    let mut graphics = MonoGraphics::new(&mut heap_display, 64, 32);
+    paint(&mut graphics).unwrap();
+    graphics.draw().unwrap();
+
+    let mut static_display = MockDisplay::new(64, 32);
+    let mut graphics: StaticMonoGraphics<64, 32, { frame_buffer_len(64, 32) }> =
+        MonoGraphics::new_static(&mut static_display).unwrap();
+    paint(&mut graphics).unwrap();
+    graphics.draw().unwrap();
+
+    assert_eq!(heap_display.calls, static_display.calls);
+}
+
+#[test]
+fn static_buffer_rejects_other_display_size() {
+    let mut display = MockDisplay::new(128, 32);
+    let graphics: esp_rs_extensa::Result<StaticMonoGraphics<64, 32, { frame_buffer_len(64, 32) }>> =
+        MonoGraphics::new_static(&mut display);
+
+    assert!(graphics.is_err());
+}
+
+#[test]
+fn borrowed_storage_tracks_dirty_rows() {
+    let mut pixels = [0u8; frame_buffer_len(64, 32)];
+    let mut dirty = [false; 32];
+    let mut display = MockDisplay::new(64, 32);
+
+    let buffer = FrameBuffer::from_storage(64, 32, 8, &mut pixels[..]).unwrap();
+    let mut graphics = MonoGraphics::with_storage(&mut display, buffer, &mut dirty[..]).unwrap();
+    graphics.draw().unwrap();
+    graphics.draw_hline(Vect2D::new(8, 3), 8, BLACK).unwrap();
+    graphics.draw().unwrap();
+
+    assert!(matches!(
+        display.calls.as_slice(),
+        [DisplayCall::Refresh(_), DisplayCall::RefreshLines(lines)] if lines.len() == 1 && lines[0].0 == 3
+    ));
+}
@@ -0,0 +1,168 @@
+use esp_rs_extensa::filesystem::read_texture_to_buffer;
+use esp_rs_extensa::graphics::{
+    encode_texture, BitOrder, FrameBuffer, HeapFrameBuffer, TextureFile, TextureHeader,
+    TEXTURE_HEADER_LEN,
+};
+use esp_rs_extensa::Error;
+
+/// A 12x3 texture with rows of 2 bytes, the second only half used.
+fn texture() -> HeapFrameBuffer {
+    FrameBuffer::from_storage(12, 3, 2, vec![0x01, 0x0F, 0x80, 0x07, 0xAA, 0x05]).unwrap()
+}
+
+fn error(bytes: &[u8]) -> String {
+    TextureFile::parse(bytes).unwrap_err().to_string()
+}
+
+#[test]
+fn encoded_textures_parse_back() {
+    for checksum in [false, true] {
+        let bytes = encode_texture(&texture(), checksum);
+        let file = TextureFile::parse(&bytes).unwrap();
+
+        assert_eq!(bytes.len(), TEXTURE_HEADER_LEN + 6);
+        assert_eq!(file.header.checksum.is_some(), checksum);
+        assert_eq!(file.as_view(), Some(texture().view()));
+        assert_eq!(file.to_frame_buffer(), texture());
+    }
+}
+
+#[test]
+fn header_layout() {
+    let bytes = encode_texture(&texture(), true);
+
+    assert_eq!(&bytes[0..4], b"SMTX");
+    assert_eq!(bytes[4], 1);
+    assert_eq!(bytes[5], 0);
+    assert_eq!(bytes[6..8], [0x02, 0x00]);
+    assert_eq!(bytes[8..10], [12, 0]);
+    assert_eq!(bytes[10..12], [3, 0]);
+    assert_eq!(bytes[12..16], [2, 0, 0, 0]);
+    // zlib.crc32(bytes([0x01, 0x0F, 0x80, 0x07, 0xAA, 0x05]))
+    assert_eq!(bytes[16..20], 0x345A_E585u32.to_le_bytes());
+}
+
+#[test]
+fn other_layouts_are_converted() {
+    let header = TextureHeader {
+        width: 12,
+        height: 3,
+        stride: 2,
+        bit_order: BitOrder::MsbFirst,
+        inverted: true,
+        checksum: None,
+    };
+    let mut bytes = header.to_bytes().to_vec();
+    bytes.extend(texture().as_bytes().iter().map(|byte| !byte.reverse_bits()));
+
+    let file = TextureFile::parse(&bytes).unwrap();
+
+    assert_eq!(file.header, header);
+    assert_eq!(file.as_view(), None);
+    assert_eq!(file.to_frame_buffer(), texture());
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    let valid = encode_texture(&texture(), true);
+
+    assert!(error(&valid[..10]).contains("too short"));
+    assert!(error(&[]).contains("too short"));
+
+    let mut bytes = valid.clone();
+    bytes[0] = b'X';
+    assert!(error(&bytes).contains("Not a texture file"));
+
+    let mut bytes = valid.clone();
+    bytes[4] = 2;
+    assert!(error(&bytes).contains("version 2"));
+
+    let mut bytes = valid.clone();
+    bytes[5] = 7;
+    assert!(error(&bytes).contains("bit order"));
+
+    let mut bytes = valid.clone();
+    bytes[7] = 0x80;
+    assert!(error(&bytes).contains("flags"));
+
+    let mut bytes = valid.clone();
+    bytes[12] = 1;
+    assert!(error(&bytes).contains("can't hold 12 pixels"));
+
+    let mut bytes = valid.clone();
+    bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[10] = 0xFF;
+    assert!(TextureFile::parse(&bytes).is_err());
+
+    assert!(error(&valid[..valid.len() - 1]).contains("needs 6 bytes of pixels, the file has 5"));
+    assert!(error(&[&valid[..], &[0]].concat()).contains("the file has 7"));
+
+    let mut bytes = valid.clone();
+    bytes[TEXTURE_HEADER_LEN] ^= 0x10;
+    assert!(error(&bytes).contains("checksum"));
+}
+
+#[test]
+fn checksum_field_must_be_zero_without_the_flag() {
+    let mut bytes = encode_texture(&texture(), false);
+    bytes[18] = 0x5A;
+
+    assert!(matches!(
+        TextureHeader::parse(&bytes),
+        Err(Error::TextureChecksumField(0x005A_0000))
+    ));
+    assert!(error(&bytes).contains("checksum field"));
+}
+
+#[test]
+fn headerless_files_are_rejected_with_their_path() {
+    let path = std::env::temp_dir().join("texture_file_headerless.img");
+    std::fs::write(&path, [0x90, 0x01, 0xF0, 0x00, 0xFF, 0xFF]).unwrap();
+
+    let error = read_texture_to_buffer(path.to_str().unwrap()).unwrap_err();
+
+    match &error {
+        Error::File { path: file, .. } => assert_eq!(file, path.to_str().unwrap()),
+        _ => panic!("{}", error),
+    }
+
+    let message = error.to_string();
+    assert!(
+        message.contains("texture_file_headerless.img"),
+        "{}",
+        message
+    );
+    assert!(!message.contains("too short"), "{}", message);
+
+    let source = std::error::Error::source(&error)
+        .and_then(|source| source.downcast_ref::<Error>())
+        .unwrap();
+    assert!(
+        matches!(source, Error::TextureTooShort { len: 6 }),
+        "{}",
+        source
+    );
+    assert!(source.to_string().contains("too short"), "{}", source);
+}
+
+#[test]
+fn missing_files_report_the_io_error_as_source() {
+    let path = std::env::temp_dir().join("texture_file_missing.img");
+    let _ = std::fs::remove_file(&path);
+
+    let error = read_texture_to_buffer(path.to_str().unwrap()).unwrap_err();
+
+    let io = std::error::Error::source(&error)
+        .and_then(std::error::Error::source)
+        .and_then(|source| source.downcast_ref::<std::io::Error>())
+        .unwrap();
+    assert_eq!(io.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn bundled_texture_is_valid() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/spiffs/land.img");
+    let texture = read_texture_to_buffer(path).unwrap();
+
+    assert_eq!((texture.width(), texture.height()), (400, 240));
+}
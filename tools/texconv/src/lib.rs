@@ -0,0 +1,171 @@
+//! Conversion between images and the `.img` texture files `MonoGraphics` draws, see
+//! `esp_rs_extensa::graphics::texture_file` for their format.
+
+use anyhow::anyhow;
+use esp_rs_extensa::graphics::{
+    encode_texture, Dither, FrameBuffer, GreyImage, HeapFrameBuffer, TextureFile,
+};
+use image::{imageops::FilterType, DynamicImage, GrayImage, Luma};
+
+/// How grey is turned into black and white.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Pixels at least this bright turn white, the others black.
+    Threshold(u8),
+    Dither(Dither),
+}
+
+/// What happens to an image on its way to a texture, in the order of the fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The part of the image to keep, as `(x, y, width, height)`.
+    pub crop: Option<(u32, u32, u32, u32)>,
+    /// The size to scale to, as `(width, height)`.
+    pub resize: Option<(u32, u32)>,
+    /// Whether to keep the aspect ratio when resizing, fitting the image within the size.
+    pub fit: bool,
+    pub mode: Mode,
+    /// Whether to swap black and white.
+    pub invert: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            crop: None,
+            resize: None,
+            fit: false,
+            mode: Mode::Threshold(128),
+            invert: false,
+        }
+    }
+}
+
+/// Crops, scales and turns `image` into black and white, with transparent pixels counting
+/// as white like the panel behind them.
+pub fn to_frame_buffer(image: &DynamicImage, options: &Options) -> anyhow::Result<HeapFrameBuffer> {
+    let mut image = image.clone();
+
+    if let Some((x, y, width, height)) = options.crop {
+        if x.saturating_add(width) > image.width() || y.saturating_add(height) > image.height() {
+            return Err(anyhow!(
+                "Can't crop {}x{} pixels at {},{} out of a {}x{} image",
+                width,
+                height,
+                x,
+                y,
+                image.width(),
+                image.height()
+            ));
+        }
+
+        image = image.crop_imm(x, y, width, height);
+    }
+
+    if let Some((width, height)) = options.resize {
+        image = if options.fit {
+            image.resize(width, height, FilterType::Lanczos3)
+        } else {
+            image.resize_exact(width, height, FilterType::Lanczos3)
+        };
+    }
+
+    let (width, height) = match (u16::try_from(image.width()), u16::try_from(image.height())) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => {
+            return Err(anyhow!(
+                "A {}x{} image is too large for a texture",
+                image.width(),
+                image.height()
+            ))
+        }
+    };
+
+    let pixels: Vec<u8> = image
+        .to_luma_alpha8()
+        .pixels()
+        .map(|pixel| {
+            let [level, alpha] = pixel.0.map(u32::from);
+            ((level * alpha + 255 * (255 - alpha)) / 255) as u8
+        })
+        .collect();
+    let grey = GreyImage::from_storage(width, height, width as usize, &pixels)?;
+
+    let mut buffer = match options.mode {
+        Mode::Dither(dither) => grey.dither(dither)?,
+        Mode::Threshold(level) => {
+            let mut buffer = FrameBuffer::new(width, height);
+
+            for y in 0..height {
+                let row = buffer.row_mut(y);
+
+                for (x, pixel) in grey.row(y).iter().enumerate() {
+                    if *pixel < level {
+                        row[x / 8] &= !(1 << (x % 8));
+                    }
+                }
+            }
+
+            buffer
+        }
+    };
+
+    if options.invert {
+        for byte in buffer.as_bytes_mut() {
+            *byte = !*byte;
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Encodes `image` as a texture file, with a checksum when `checksum` is set.
+pub fn to_texture(
+    image: &DynamicImage,
+    options: &Options,
+    checksum: bool,
+) -> anyhow::Result<Vec<u8>> {
+    Ok(encode_texture(&to_frame_buffer(image, options)?, checksum))
+}
+
+/// Decodes a texture file into a black and white image, for inspection.
+pub fn from_texture(bytes: &[u8]) -> anyhow::Result<GrayImage> {
+    let texture = TextureFile::parse(bytes)?.to_frame_buffer();
+
+    Ok(GrayImage::from_fn(
+        texture.width() as u32,
+        texture.height() as u32,
+        |x, y| {
+            let white = texture.row(y as u16)[x as usize / 8] & (1 << (x % 8)) != 0;
+            Luma([if white { 255 } else { 0 }])
+        },
+    ))
+}
+
+/// Parses a size given as `WIDTHxHEIGHT`.
+pub fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let parsed = size
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)));
+
+    match parsed {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!("expected WIDTHxHEIGHT, got \"{}\"", size)),
+    }
+}
+
+/// Parses an area given as `X,Y,WIDTHxHEIGHT`.
+pub fn parse_crop(area: &str) -> Result<(u32, u32, u32, u32), String> {
+    let error = || format!("expected X,Y,WIDTHxHEIGHT, got \"{}\"", area);
+    let mut fields = area.splitn(3, ',');
+
+    let (Some(x), Some(y), Some(size)) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(error());
+    };
+    let (width, height) = parse_size(size).map_err(|_| error())?;
+
+    match (x.parse(), y.parse()) {
+        (Ok(x), Ok(y)) => Ok((x, y, width, height)),
+        _ => Err(error()),
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use clap::{Parser, ValueEnum};
+use esp_rs_extensa::graphics::Dither;
+use texconv::{from_texture, parse_crop, parse_size, to_texture, Mode, Options};
+
+/// Converts PNG, BMP, PBM and JPEG images into the `.img` textures `MonoGraphics` draws,
+/// or a texture back into an image to check it.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// The image to convert, or a `.img` texture to turn back into an image
+    input: PathBuf,
+
+    /// Where to write the texture, or the image in the format its extension names
+    output: PathBuf,
+
+    /// Dither the image instead of thresholding it
+    #[arg(long, value_enum)]
+    dither: Option<DitherArg>,
+
+    /// Grey level from 0 to 255 from which pixels turn white, when not dithering
+    #[arg(long, default_value_t = 128, conflicts_with = "dither")]
+    threshold: u8,
+
+    /// Swap black and white
+    #[arg(long)]
+    invert: bool,
+
+    /// Keep only the given area of the image, before resizing
+    #[arg(long, value_name = "X,Y,WIDTHxHEIGHT", value_parser = parse_crop)]
+    crop: Option<(u32, u32, u32, u32)>,
+
+    /// Scale the image to the given size
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    resize: Option<(u32, u32)>,
+
+    /// Keep the aspect ratio when resizing, fitting the image within the size
+    #[arg(long, requires = "resize")]
+    fit: bool,
+
+    /// Leave the checksum out of the texture
+    #[arg(long)]
+    no_checksum: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DitherArg {
+    Bayer,
+    FloydSteinberg,
+    Atkinson,
+}
+
+impl From<DitherArg> for Dither {
+    fn from(dither: DitherArg) -> Self {
+        match dither {
+            DitherArg::Bayer => Dither::Bayer,
+            DitherArg::FloydSteinberg => Dither::FloydSteinberg,
+            DitherArg::Atkinson => Dither::Atkinson,
+        }
+    }
+}
+
+fn is_texture(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("img"))
+}
+
+/// Prefixes an error with the file it's about.
+fn in_file<E: Display>(path: &Path) -> impl FnOnce(E) -> anyhow::Error + '_ {
+    move |err| anyhow!("{}: {}", path.display(), err)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    if is_texture(&args.input) {
+        let bytes = fs::read(&args.input).map_err(in_file(&args.input))?;
+        let image = from_texture(&bytes).map_err(in_file(&args.input))?;
+
+        image.save(&args.output).map_err(in_file(&args.output))?;
+
+        return Ok(());
+    }
+
+    let options = Options {
+        crop: args.crop,
+        resize: args.resize,
+        fit: args.fit,
+        mode: match args.dither {
+            Some(dither) => Mode::Dither(dither.into()),
+            None => Mode::Threshold(args.threshold),
+        },
+        invert: args.invert,
+    };
+
+    let image = image::open(&args.input).map_err(in_file(&args.input))?;
+    let texture = to_texture(&image, &options, !args.no_checksum).map_err(in_file(&args.input))?;
+
+    fs::write(&args.output, texture).map_err(in_file(&args.output))?;
+
+    Ok(())
+}
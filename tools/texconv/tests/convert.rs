@@ -0,0 +1,137 @@
+use esp_rs_extensa::graphics::{Dither, TextureFile};
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use texconv::{from_texture, parse_crop, parse_size, to_frame_buffer, to_texture, Mode, Options};
+
+/// A 21x10 image of black and white stripes of different widths.
+fn stripes() -> GrayImage {
+    GrayImage::from_fn(21, 10, |x, y| {
+        Luma([if (x / 3 + y / 2) % 2 == 0 { 255 } else { 0 }])
+    })
+}
+
+fn lit(image: &GrayImage) -> usize {
+    image.pixels().filter(|pixel| pixel.0[0] == 255).count()
+}
+
+#[test]
+fn black_and_white_images_survive_a_round_trip() {
+    let image = DynamicImage::ImageLuma8(stripes());
+
+    for checksum in [false, true] {
+        let texture = to_texture(&image, &Options::default(), checksum).unwrap();
+
+        assert_eq!(
+            TextureFile::parse(&texture)
+                .unwrap()
+                .header
+                .checksum
+                .is_some(),
+            checksum
+        );
+        assert_eq!(from_texture(&texture).unwrap(), stripes());
+    }
+}
+
+#[test]
+fn inverting_swaps_black_and_white() {
+    let image = DynamicImage::ImageLuma8(stripes());
+    let options = Options {
+        invert: true,
+        ..Options::default()
+    };
+    let texture = from_texture(&to_texture(&image, &options, true).unwrap()).unwrap();
+
+    for (converted, original) in texture.pixels().zip(stripes().pixels()) {
+        assert_eq!(converted.0[0], 255 - original.0[0]);
+    }
+}
+
+#[test]
+fn threshold_and_dithering() {
+    let grey = DynamicImage::ImageLuma8(GrayImage::from_pixel(32, 32, Luma([100])));
+
+    for (mode, expected) in [
+        (Mode::Threshold(100), 32 * 32),
+        (Mode::Threshold(101), 0),
+        (Mode::Dither(Dither::Bayer), 32 * 32 * 100 / 255),
+    ] {
+        let options = Options {
+            mode,
+            ..Options::default()
+        };
+        let texture = from_texture(&to_texture(&grey, &options, true).unwrap()).unwrap();
+
+        assert!(lit(&texture).abs_diff(expected) <= 16, "{:?}", mode);
+    }
+}
+
+#[test]
+fn dithering_wide_images() {
+    let grey = DynamicImage::ImageLuma8(GrayImage::from_pixel(800, 4, Luma([100])));
+
+    for dither in [Dither::FloydSteinberg, Dither::Atkinson] {
+        let options = Options {
+            mode: Mode::Dither(dither),
+            ..Options::default()
+        };
+        let buffer = to_frame_buffer(&grey, &options).unwrap();
+
+        assert_eq!((buffer.width(), buffer.height()), (800, 4));
+    }
+}
+
+#[test]
+fn transparent_pixels_are_white() {
+    let image = RgbaImage::from_fn(8, 4, |x, _| Rgba([0, 0, 0, if x < 4 { 0 } else { 255 }]));
+    let buffer = to_frame_buffer(&DynamicImage::ImageRgba8(image), &Options::default()).unwrap();
+
+    assert!(buffer.rows().all(|row| row == [0x0F]));
+}
+
+#[test]
+fn cropping_and_resizing() {
+    let image = DynamicImage::ImageLuma8(GrayImage::new(40, 20));
+
+    for (crop, resize, fit, size) in [
+        (Some((5, 2, 10, 8)), None, false, (10, 8)),
+        (None, Some((30, 30)), false, (30, 30)),
+        (None, Some((30, 30)), true, (30, 15)),
+        (Some((0, 0, 20, 20)), Some((10, 40)), true, (10, 10)),
+    ] {
+        let options = Options {
+            crop,
+            resize,
+            fit,
+            ..Options::default()
+        };
+        let buffer = to_frame_buffer(&image, &options).unwrap();
+
+        assert_eq!((buffer.width() as u32, buffer.height() as u32), size);
+    }
+
+    let options = Options {
+        crop: Some((30, 0, 20, 20)),
+        ..Options::default()
+    };
+    assert!(to_frame_buffer(&image, &options).is_err());
+}
+
+#[test]
+fn sizes_and_areas() {
+    assert_eq!(parse_size("400x240"), Ok((400, 240)));
+    assert_eq!(parse_crop("10,20,30x40"), Ok((10, 20, 30, 40)));
+
+    for size in ["400", "x240", "400x", "0x10", "ax2", "-1x2"] {
+        assert!(parse_size(size).is_err(), "{}", size);
+    }
+
+    for area in ["10,20", "10,20,30", "a,20,30x40", "10,20,30x40,1"] {
+        assert!(parse_crop(area).is_err(), "{}", area);
+    }
+}
+
+#[test]
+fn malformed_textures_are_rejected() {
+    assert!(from_texture(b"SMTX").is_err());
+    assert!(from_texture(&[0x90, 0x01, 0xF0, 0x00]).is_err());
+}